@@ -0,0 +1,317 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Instant,
+};
+
+use crate::{
+    config::Config,
+    pipeline::{self, Options, Summary},
+    reddit::{ChoosePost, Sort, Time},
+};
+
+/// List of jobs to render, parsed from toml file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Batch {
+    /// Amount of jobs to run at once
+    #[serde(default = "default_parallel")]
+    pub parallel: usize,
+    /// Jobs, in order, as `[[job]]` tables
+    #[serde(rename = "job", default)]
+    pub jobs: Vec<BatchJob>,
+}
+
+fn default_parallel() -> usize {
+    1
+}
+
+impl std::str::FromStr for Batch {
+    type Err = toml::de::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s)
+    }
+}
+
+/// Settings of one job, overriding config file
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchJob {
+    /// Name of job in report, defaulting to subreddit
+    pub name: Option<String>,
+    pub subreddit: Option<String>,
+    pub sort: Option<Sort>,
+    pub time: Option<Time>,
+    /// Take comments of a post, instead of posts of subreddit
+    pub comments: Option<bool>,
+    /// Id of post to take comments from
+    pub post: Option<String>,
+    /// How to choose post to take comments from, if `post` is not set
+    pub choose: Option<ChoosePost>,
+    pub limit: Option<usize>,
+    /// Filename template of video
+    pub output: Option<String>,
+    /// Directory to save video in
+    pub dir: Option<String>,
+}
+
+impl BatchJob {
+    /// Name of job, or subreddit, or position in list
+    pub fn name(&self, index: usize) -> String {
+        self.name
+            .clone()
+            .or_else(|| {
+                self.subreddit
+                    .as_ref()
+                    .map(|subreddit| format!("r/{subreddit}"))
+            })
+            .unwrap_or_else(|| format!("job {}", index + 1))
+    }
+
+    /// Override settings of config
+    ///
+    /// Posts cannot be chosen interactively in batch, so taking comments requires a post id
+    pub fn apply(&self, config: &mut Config) -> Result<(), String> {
+        let reddit = &mut config.reddit;
+        if let Some(subreddit) = &self.subreddit {
+            reddit.subreddit = subreddit.as_str().into();
+        }
+        if let Some(sort) = self.sort {
+            reddit.sort = sort;
+        }
+        if let Some(time) = self.time {
+            reddit.time = time;
+        }
+        if let Some(comments) = self.comments {
+            reddit.comments = comments;
+        }
+        if let Some(post) = &self.post {
+            reddit.post = Some(post.clone());
+        }
+        if let Some(limit) = self.limit {
+            reddit.limit = limit;
+        }
+        if let Some(output) = &self.output {
+            config.out.name = output.clone();
+        }
+        if let Some(dir) = &self.dir {
+            config.out.dir = dir.clone();
+        }
+
+        if let Some(choose) = self.choose {
+            config.reddit.choose = choose;
+        }
+
+        let reddit = &config.reddit;
+        if reddit.comments && reddit.post.is_none() && reddit.choose == ChoosePost::Prompt {
+            return Err(
+                "Taking comments in batch requires `post`, or `choose = \"first\"`".to_string(),
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Result of one job
+#[derive(Debug, Serialize)]
+pub struct JobReport {
+    /// Name of job
+    pub name: String,
+    /// Id of job directory, if created
+    pub job_id: Option<String>,
+    /// Files which were saved
+    pub files: Vec<String>,
+    /// Error, if job failed
+    pub error: Option<String>,
+    /// Time taken, in seconds
+    pub seconds: f32,
+}
+
+impl JobReport {
+    /// Whether job succeeded
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Run all jobs of batch, with each job using config parsed from `config_text`
+///
+/// Each job runs in isolation, so a job which fails or panics does not stop other jobs. Reports
+/// are returned in order of jobs.
+pub fn run_batch(
+    config_text: &str,
+    batch: &Batch,
+    options: &Options,
+    parallel: usize,
+) -> Vec<JobReport> {
+    let next = AtomicUsize::new(0);
+    let reports = Mutex::new(Vec::new());
+
+    thread::scope(|scope| {
+        for _ in 0..parallel.clamp(1, batch.jobs.len().max(1)) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(job) = batch.jobs.get(index) else {
+                    break;
+                };
+                let report = run_job(config_text, job, index, options);
+                reports
+                    .lock()
+                    .unwrap_or_else(|err| err.into_inner())
+                    .push((index, report));
+            });
+        }
+    });
+
+    let mut reports = reports.into_inner().unwrap_or_else(|err| err.into_inner());
+    reports.sort_by_key(|(index, _)| *index);
+    reports.into_iter().map(|(_, report)| report).collect()
+}
+
+/// Run one job, catching errors and panics
+fn run_job(config_text: &str, job: &BatchJob, index: usize, options: &Options) -> JobReport {
+    let name = job.name(index);
+    println!("[batch] Starting {name}...");
+    let start = Instant::now();
+
+    let result = config_text
+        .parse::<Config>()
+        .map_err(|err| format!("Failed to parse config file - {err}"))
+        .and_then(|mut config| {
+            job.apply(&mut config)?;
+            run_isolated(&config, options)
+        });
+
+    let seconds = start.elapsed().as_secs_f32();
+    match result {
+        Ok(summary) => {
+            println!("[batch] Finished {name}");
+            JobReport {
+                name,
+                job_id: Some(summary.job_id),
+                files: summary.files,
+                error: None,
+                seconds,
+            }
+        }
+        Err(err) => {
+            eprintln!("[batch] Failed {name} - {err}");
+            JobReport {
+                name,
+                job_id: None,
+                files: Vec::new(),
+                error: Some(err),
+                seconds,
+            }
+        }
+    }
+}
+
+/// Run pipeline, catching panics as errors
+pub fn run_isolated(config: &Config, options: &Options) -> Result<Summary, String> {
+    panic::catch_unwind(AssertUnwindSafe(|| pipeline::run(config, options)))
+        .unwrap_or_else(|panic| Err(panic_message(panic)))
+}
+
+/// Get message of panic payload
+fn panic_message(panic: Box<dyn std::any::Any + Send>) -> String {
+    let message = match panic.downcast::<String>() {
+        Ok(message) => *message,
+        Err(panic) => match panic.downcast::<&str>() {
+            Ok(message) => message.to_string(),
+            Err(_) => "Unknown error".to_string(),
+        },
+    };
+    format!("Panicked: {message}")
+}
+
+/// Print table of results of batch
+pub fn print_report(reports: &[JobReport]) {
+    let width = reports
+        .iter()
+        .map(|report| report.name.chars().count())
+        .max()
+        .unwrap_or_default();
+
+    for report in reports {
+        let JobReport {
+            name,
+            files,
+            error,
+            seconds,
+            ..
+        } = report;
+        match error {
+            None => println!(
+                "  ok     {name:width$}  {seconds:>6.1}s  {} files",
+                files.len()
+            ),
+            Some(err) => println!("  failed {name:width$}  {seconds:>6.1}s  {err}"),
+        }
+    }
+
+    let failed = reports.iter().filter(|report| !report.is_success()).count();
+    let succeeded = reports.len() - failed;
+    println!("{succeeded} succeeded, {failed} failed");
+}
+
+/// Save report as JSON next to batch file, as `<name>.report.json`, and return path
+pub fn save_report(batch_path: &str, reports: &[JobReport]) -> Result<String, String> {
+    let path = Path::new(batch_path)
+        .with_extension("report.json")
+        .to_string_lossy()
+        .to_string();
+    let json = serde_json::to_string_pretty(reports).expect("Failed to serialize report");
+    fs::write(&path, json).map_err(|err| format!("Failed to save report - {err}"))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jobs_override_config() {
+        let batch: Batch = r#"
+            parallel = 2
+
+            [[job]]
+            subreddit = "jokes"
+            comments = false
+            output = "{subreddit}-{date}.mp4"
+
+            [[job]]
+            name = "advice"
+            post = "abc123"
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(batch.parallel, 2);
+        assert_eq!(batch.jobs[0].name(0), "r/jokes");
+        assert_eq!(batch.jobs[1].name(1), "advice");
+
+        let mut config = Config::default();
+        batch.jobs[0].apply(&mut config).unwrap();
+        assert_eq!(config.reddit.subreddit.to_string(), "r/jokes");
+        assert_eq!(config.out.name, "{subreddit}-{date}.mp4");
+
+        let mut config = Config::default();
+        batch.jobs[1].apply(&mut config).unwrap();
+        assert_eq!(config.reddit.post.as_deref(), Some("abc123"));
+
+        // Comments without post would need a prompt
+        let mut config = Config::default();
+        assert!(BatchJob::default().apply(&mut config).is_err());
+
+        // Schedules are only run by daemon
+        let scheduled = "[[job]]\nsubreddit = \"jokes\"\nschedule = \"@daily\"";
+        assert!(scheduled.parse::<Batch>().is_err());
+    }
+}
@@ -0,0 +1,312 @@
+/// Thumbnail images, with title over background frame
+mod thumbnail;
+
+use ab_glyph::{FontVec, PxScale};
+use image::{Rgba, RgbaImage};
+use imageproc::{
+    drawing::{
+        draw_filled_circle_mut, draw_filled_rect_mut, draw_polygon_mut, draw_text_mut, text_size,
+    },
+    point::Point,
+    rect::Rect,
+};
+use serde::Deserialize;
+use std::fs;
+
+use crate::{config, reddit::format_number, Text, TextKind};
+
+pub use self::thumbnail::{render_thumbnail, THUMBNAIL_HEIGHT, THUMBNAIL_WIDTH};
+
+/// Maximum height of card, as percentage of height of frame
+const MAX_CARD_HEIGHT_PERCENT: u32 = 80;
+/// Smallest font size of body text, when shrinking card to fit frame
+const MIN_FONT_SIZE: f32 = 14.0;
+
+/// Color scheme of cards
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    Light,
+    #[default]
+    Dark,
+}
+
+/// Colors used to draw card
+struct Palette {
+    background: Rgba<u8>,
+    text: Rgba<u8>,
+    secondary: Rgba<u8>,
+    accent: Rgba<u8>,
+}
+
+impl Theme {
+    fn palette(self) -> Palette {
+        match self {
+            Self::Light => Palette {
+                background: Rgba([255, 255, 255, 240]),
+                text: Rgba([26, 26, 27, 255]),
+                secondary: Rgba([120, 124, 126, 255]),
+                accent: Rgba([255, 69, 0, 255]),
+            },
+            Self::Dark => Palette {
+                background: Rgba([26, 26, 27, 235]),
+                text: Rgba([215, 218, 220, 255]),
+                secondary: Rgba([129, 131, 132, 255]),
+                accent: Rgba([255, 69, 0, 255]),
+            },
+        }
+    }
+}
+
+/// Load TrueType or OpenType font from file
+pub fn load_font(path: &str) -> Result<FontVec, String> {
+    let bytes = fs::read(path).map_err(|err| format!("Failed to read font '{path}' - {err}"))?;
+    FontVec::try_from_vec(bytes).map_err(|err| format!("Failed to parse font '{path}' - {err}"))
+}
+
+/// Draw text frame as a Reddit-style card, with transparent corners
+///
+/// Width and font size of config are for a 1080p frame, and are scaled to resolution of `frame`.
+/// Font is made smaller, if card would not fit in frame.
+pub fn render_card(
+    config: &config::Cards,
+    font: &FontVec,
+    text: &Text,
+    frame: (u32, u32),
+) -> RgbaImage {
+    let (frame_width, frame_height) = frame;
+    let scale = frame_width.min(frame_height) as f32 / 1080.0;
+    let width = ((config.width as f32 * scale) as u32).min(frame_width * 9 / 10);
+    let max_height = frame_height * MAX_CARD_HEIGHT_PERCENT / 100;
+
+    let mut base_size = config.font_size * scale;
+    loop {
+        let image = draw_card(config, font, text, width, base_size);
+        if image.height() <= max_height || base_size <= MIN_FONT_SIZE {
+            return image;
+        }
+        base_size = (base_size * 0.9).max(MIN_FONT_SIZE);
+    }
+}
+
+/// Draw card with given width, and font size of body text
+fn draw_card(
+    config: &config::Cards,
+    font: &FontVec,
+    text: &Text,
+    width: u32,
+    base_size: f32,
+) -> RgbaImage {
+    let palette = config.theme.palette();
+
+    // Sizes relative to body font size
+    let font_size = match text.kind {
+        TextKind::Title | TextKind::Intro => base_size * 1.15,
+        _ => base_size,
+    };
+    let padding = (base_size * 0.9) as i32;
+    let header_size = base_size * 0.75;
+    let header_height = (header_size * 1.6) as i32;
+    let line_height = (font_size * 1.3) as i32;
+    let gap = (base_size * 0.5) as i32;
+
+    let lines = wrap_text_pixels(
+        font,
+        font_size,
+        &text.content,
+        width.saturating_sub(padding as u32 * 2),
+    );
+
+    // Header and footer are only shown for texts from a post or comment
+    let source = text.source.as_ref();
+    let header = if source.is_some() {
+        header_height + gap
+    } else {
+        0
+    };
+    let footer = if source.is_some() {
+        gap + header_height
+    } else {
+        0
+    };
+    let height = padding * 2 + header + lines.len() as i32 * line_height + footer;
+
+    let mut image = RgbaImage::new(width, height as u32);
+    draw_rounded_rect(&mut image, (base_size * 0.6) as i32, palette.background);
+
+    let mut y = padding;
+
+    if let Some(source) = source {
+        let mut x = padding;
+
+        // Placeholder avatar, colored by username
+        if config.avatar {
+            let radius = header_height / 2;
+            draw_filled_circle_mut(
+                &mut image,
+                (x + radius, y + radius),
+                radius,
+                avatar_color(&source.author),
+            );
+            x += header_height + gap / 2;
+        }
+
+        let text_y = y + (header_height - header_size as i32) / 2;
+        let subreddit = format!("r/{}", source.subreddit);
+        draw_text_mut(
+            &mut image,
+            palette.text,
+            x,
+            text_y,
+            PxScale::from(header_size),
+            font,
+            &subreddit,
+        );
+        x += text_size(PxScale::from(header_size), font, &subreddit).0 as i32;
+        draw_text_mut(
+            &mut image,
+            palette.secondary,
+            x,
+            text_y,
+            PxScale::from(header_size),
+            font,
+            &format!(" · u/{}", source.author),
+        );
+
+        y += header;
+    }
+
+    for line in &lines {
+        draw_text_mut(
+            &mut image,
+            palette.text,
+            padding,
+            y,
+            PxScale::from(font_size),
+            font,
+            line,
+        );
+        y += line_height;
+    }
+
+    if let Some(source) = source {
+        y += gap;
+
+        // Upvote arrow
+        let size = header_size as i32;
+        let top = y + (header_height - size) / 2;
+        draw_polygon_mut(
+            &mut image,
+            &[
+                Point::new(padding + size / 2, top),
+                Point::new(padding + size, top + size),
+                Point::new(padding, top + size),
+            ],
+            palette.accent,
+        );
+
+        draw_text_mut(
+            &mut image,
+            palette.secondary,
+            padding + size + gap / 2,
+            y + (header_height - header_size as i32) / 2,
+            PxScale::from(header_size),
+            font,
+            format_number(source.score).trim(),
+        );
+    }
+
+    image
+}
+
+/// Fill image with rectangle with rounded corners
+fn draw_rounded_rect(image: &mut RgbaImage, radius: i32, color: Rgba<u8>) {
+    let (width, height) = (image.width() as i32, image.height() as i32);
+    let radius = radius.min(width / 2).min(height / 2);
+
+    // Horizontal and vertical bands, not covering corners
+    draw_filled_rect_mut(
+        image,
+        Rect::at(radius, 0).of_size((width - radius * 2) as u32, height as u32),
+        color,
+    );
+    draw_filled_rect_mut(
+        image,
+        Rect::at(0, radius).of_size(width as u32, (height - radius * 2) as u32),
+        color,
+    );
+
+    // Corners
+    for (x, y) in [
+        (radius, radius),
+        (width - radius - 1, radius),
+        (radius, height - radius - 1),
+        (width - radius - 1, height - radius - 1),
+    ] {
+        draw_filled_circle_mut(image, (x, y), radius, color);
+    }
+}
+
+/// Color of placeholder avatar, derived from username
+fn avatar_color(username: &str) -> Rgba<u8> {
+    const COLORS: &[[u8; 3]] = &[
+        [255, 69, 0],
+        [0, 121, 211],
+        [70, 209, 96],
+        [255, 180, 0],
+        [148, 224, 68],
+        [127, 85, 196],
+        [255, 102, 172],
+    ];
+    let hash = username.bytes().fold(0usize, |hash, byte| {
+        hash.wrapping_mul(31).wrapping_add(byte as usize)
+    });
+    let [r, g, b] = COLORS[hash % COLORS.len()];
+    Rgba([r, g, b, 255])
+}
+
+/// Wrap text to fit within a maximum width in pixels
+///
+/// Words wider than `max_width` are split between characters
+pub fn wrap_text_pixels(font: &FontVec, size: f32, text: &str, max_width: u32) -> Vec<String> {
+    let scale = PxScale::from(size);
+    let fits = |text: &str| text_size(scale, font, text).0 <= max_width;
+
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+
+        for word in paragraph.split_whitespace() {
+            let joined = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{line} {word}")
+            };
+            if fits(&joined) {
+                line = joined;
+                continue;
+            }
+
+            // Start new line with word
+            if !line.is_empty() {
+                lines.push(line);
+            }
+            line = String::new();
+
+            // Split word, if too long for a single line
+            for char in word.chars() {
+                line.push(char);
+                if !fits(&line) && line.chars().count() > 1 {
+                    line.pop();
+                    lines.push(line);
+                    line = char.to_string();
+                }
+            }
+        }
+
+        if !line.is_empty() {
+            lines.push(line);
+        }
+    }
+    lines
+}
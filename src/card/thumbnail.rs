@@ -0,0 +1,130 @@
+use ab_glyph::{FontVec, PxScale};
+use image::{Rgba, RgbaImage};
+use imageproc::drawing::{draw_text_mut, text_size};
+
+use super::wrap_text_pixels;
+
+/// Width of thumbnail, in pixels
+pub const THUMBNAIL_WIDTH: u32 = 1280;
+/// Height of thumbnail, in pixels
+pub const THUMBNAIL_HEIGHT: u32 = 720;
+
+/// Space between edges of thumbnail and text
+const MARGIN: i32 = 60;
+/// Largest and smallest font size of title, in pixels
+const TITLE_SIZES: (u32, u32) = (120, 40);
+const HEADER_SIZE: f32 = 52.0;
+const WATERMARK_SIZE: f32 = 36.0;
+
+const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const SHADOW: Rgba<u8> = Rgba([0, 0, 0, 255]);
+const ACCENT: Rgba<u8> = Rgba([255, 69, 0, 255]);
+
+/// Draw thumbnail over background frame, with title, subreddit name, and optional watermark
+///
+/// Background should be the size of the thumbnail. Title is wrapped, and sized as large as fits.
+pub fn render_thumbnail(
+    font: &FontVec,
+    mut image: RgbaImage,
+    title: &str,
+    subreddit: Option<&str>,
+    watermark: Option<&str>,
+) -> RgbaImage {
+    let (width, height) = image.dimensions();
+
+    // Darken background, so text stands out
+    for pixel in image.pixels_mut() {
+        for channel in &mut pixel.0[..3] {
+            *channel = (*channel as f32 * 0.55) as u8;
+        }
+    }
+
+    let mut top = MARGIN;
+    if let Some(subreddit) = subreddit {
+        draw_shadowed_text(
+            &mut image,
+            ACCENT,
+            MARGIN,
+            top,
+            HEADER_SIZE,
+            font,
+            &format!("r/{subreddit}"),
+        );
+        top += (HEADER_SIZE * 1.5) as i32;
+    }
+    let bottom = height as i32 - MARGIN - (WATERMARK_SIZE * 1.5) as i32;
+
+    // Largest font size which fits title in space between header and watermark
+    let max_width = width - MARGIN as u32 * 2;
+    let max_height = (bottom - top).max(0) as f32;
+    let (size, lines) = (TITLE_SIZES.1..=TITLE_SIZES.0)
+        .rev()
+        .step_by(4)
+        .map(|size| {
+            let size = size as f32;
+            (size, wrap_text_pixels(font, size, title, max_width))
+        })
+        .find(|(size, lines)| lines.len() as f32 * size * 1.15 <= max_height)
+        .unwrap_or_else(|| {
+            let size = TITLE_SIZES.1 as f32;
+            (size, wrap_text_pixels(font, size, title, max_width))
+        });
+
+    // Center title vertically in space
+    let line_height = (size * 1.15) as i32;
+    let mut y = top + (bottom - top - lines.len() as i32 * line_height).max(0) / 2;
+    for line in &lines {
+        draw_shadowed_text(&mut image, WHITE, MARGIN, y, size, font, line);
+        y += line_height;
+    }
+
+    if let Some(watermark) = watermark {
+        let (text_width, _) = text_size(PxScale::from(WATERMARK_SIZE), font, watermark);
+        draw_shadowed_text(
+            &mut image,
+            WHITE,
+            width as i32 - MARGIN - text_width as i32,
+            height as i32 - MARGIN - WATERMARK_SIZE as i32,
+            WATERMARK_SIZE,
+            font,
+            watermark,
+        );
+    }
+
+    image
+}
+
+/// Draw text with dark outline, to be readable over any background
+fn draw_shadowed_text(
+    image: &mut RgbaImage,
+    color: Rgba<u8>,
+    x: i32,
+    y: i32,
+    size: f32,
+    font: &FontVec,
+    text: &str,
+) {
+    let scale = PxScale::from(size);
+    let offset = (size / 24.0).ceil() as i32;
+    for (dx, dy) in [
+        (-1, -1),
+        (0, -1),
+        (1, -1),
+        (-1, 0),
+        (1, 0),
+        (-1, 1),
+        (0, 1),
+        (1, 1),
+    ] {
+        draw_text_mut(
+            image,
+            SHADOW,
+            x + dx * offset,
+            y + dy * offset,
+            scale,
+            font,
+            text,
+        );
+    }
+    draw_text_mut(image, color, x, y, scale, font, text);
+}
@@ -1,36 +1,241 @@
 #[macro_use]
 mod macros;
 
+use crate::card::Theme;
+use crate::job::Cleanup;
+use crate::reddit::{ChoosePost, CommentSort, Merge, Sort, Time};
+use crate::video::{Collision, Container, Fit, Profile, Quality, VideoCodec};
+
 destructs! {
     #[derive(Debug)]
     pub struct Config: Default {
         assets: Assets = Default,
+        cards: Cards = Default,
+        ffmpeg: FFMpeg = Default,
+        intro: Intro = Default,
+        metadata: Metadata = Default,
+        outro: Outro = Default,
+        music: Music = Default,
         out: Out = Default,
+        pause: Pause = Default,
+        preview: Preview = Default,
         reddit: Reddit = Default,
+        thumbnail: Thumbnail = Default,
         voice: Voice = Default,
     }
 
     #[derive(Debug)]
     pub struct Assets: Default {
-        background: String = "background.mp4",
+        /// Background video file, directory of videos, or list of either
+        background: MediaSource = "background.mp4",
+        /// Start background from random time, instead of beginning
+        random_offset: bool = true,
+        /// Seed for choosing background clip and offset, random if not set
+        seed: Option<u64> = None,
         watermark: Option<String> = None,
+        /// Background music file, directory of tracks, or list of either
+        music: Option<MediaSource> = None,
+    }
+
+    #[derive(Debug)]
+    pub struct Cards: Default {
+        /// Show text frames as Reddit-style cards, instead of plain text
+        enabled: bool = false,
+        theme: Theme = Default,
+        /// Path to TrueType or OpenType font file
+        font: String = "font.ttf",
+        /// Width of card in pixels, at 1080p, scaled to resolution of video
+        width: u32 = 900u32,
+        /// Font size of body text in pixels, at 1080p, reduced if card would not fit in video
+        font_size: f32 = 36.0,
+        /// Show placeholder avatar next to username
+        avatar: bool = true,
+    }
+
+    #[derive(Debug)]
+    pub struct FFMpeg: Default {
+        /// Path to ffmpeg executable, or name to find in PATH
+        path: String = "ffmpeg",
+        /// Path to ffprobe executable, or name to find in PATH
+        probe_path: String = "ffprobe",
+    }
+
+    #[derive(Debug)]
+    pub struct Intro: Default {
+        /// Show title card of post, with subreddit and score, at start of video
+        enabled: bool = false,
+        /// Text to speak during intro, with `{subreddit}` replaced, or silent if not set
+        speech: Option<String> = None,
+        /// Length of silent intro, in seconds
+        length: f32 = 3.0,
+    }
+
+    #[derive(Debug)]
+    pub struct Outro: Default {
+        /// Call-to-action text, shown during outro
+        text: Option<String> = None,
+        /// End-screen image, shown during outro
+        image: Option<String> = None,
+        /// Deprecated, use `outro` of `[out]` instead. Overrides it if set
+        length: Option<f32> = None,
+    }
+
+    #[derive(Debug)]
+    pub struct Metadata: Default {
+        /// Save upload metadata next to each video
+        enabled: bool = false,
+        format: MetadataFormat = Default,
+        /// Title of video, with `{title}`, `{subreddit}`, and `{author}` of post replaced
+        title: String = "{title} (r/{subreddit})",
+        /// Text at start of description, before source and credits
+        description: Option<String> = None,
+        /// Tags added to tags from subreddit and flair
+        tags: Vec<String> = Default,
+    }
+
+    #[derive(Debug)]
+    pub struct Music: Default {
+        /// Volume of music, relative to original
+        volume: f32 = 0.2,
+        /// Length of fade in at start, in seconds
+        fade_in: f32 = 1.0,
+        /// Length of fade out at end, in seconds
+        fade_out: f32 = 2.0,
+        /// Lower volume of music while speech plays
+        ducking: bool = true,
+        /// Volume of speech which triggers ducking (0 to 1)
+        duck_threshold: f32 = 0.02,
+        /// How much to lower volume of music while ducking
+        duck_ratio: f32 = 8.0,
     }
 
     #[derive(Debug)]
     pub struct Out: Default {
+        /// Filename of video, with `{subreddit}`, `{post_id}`, `{title}`, `{date}`, `{time}`,
+        /// and `{part}` replaced
         name: String = "video.mp4",
-        overwrite: bool = true,
+        /// Directory to save videos and files next to them
+        dir: String = ".",
+        /// How to handle output file which already exists
+        collision: Collision = Default,
+        /// Deprecated, use `collision` instead. Overrides it if set, with `false` as `error`
+        overwrite: Option<bool> = None,
+        /// Record produced files in `manifest.json` of output directory
+        manifest: bool = true,
+        /// Directory to create job directories in, defaulting to system temp directory
+        work_dir: Option<String> = None,
+        /// When to remove job directory (`success`, `always`, or `never`)
+        cleanup: Cleanup = Default,
+        /// Remove job directories older than this many hours, which are not in use, when starting
+        max_job_age: Option<f32> = None,
+        /// Length of outro after final text frame, in seconds
+        outro: f32 = 2.0,
+        /// Silence before first text frame, in seconds
+        leading_silence: f32 = 0.0,
+        /// Silence after final text frame, before outro, in seconds
+        trailing_padding: f32 = 0.0,
+        /// Resolution, text layout, and duration cap
+        profile: Profile = Default,
+        /// How to fit background into profile resolution
+        fit: Fit = Default,
+        /// Maximum length of video in seconds, overriding cap of profile
+        max_duration: Option<f32> = None,
+        /// Split video into multiple parts, instead of trimming to maximum length
+        split: bool = false,
+        /// Maximum length of each part in seconds, defaulting to maximum length of video
+        part_duration: Option<f32> = None,
+        /// Filename of each part, if `name` has no `{part}`, with the same fields replaced
+        part_name: String = "video-part{part}.mp4",
+        /// Quality preset (`draft`, `standard`, or `upload`), for encoder settings not set below
+        quality: Quality = Default,
+        /// Video codec (`x264`, `x265`, `vp9`, or `av1`)
+        video_codec: Option<VideoCodec> = None,
+        /// Constant rate factor (lower is higher quality)
+        crf: Option<u32> = None,
+        /// Video bitrate (such as `8M`), instead of constant rate factor
+        bitrate: Option<String> = None,
+        /// Encoder speed preset (such as `medium` for x264, or `6` for av1)
+        preset: Option<String> = None,
+        /// Pixel format
+        pix_fmt: Option<String> = None,
+        /// Frame rate, keeping background frame rate if not set
+        fps: Option<f32> = None,
+        /// Audio codec (such as `aac` or `libopus`)
+        audio_codec: Option<String> = None,
+        /// Audio bitrate (such as `192k`)
+        audio_bitrate: Option<String> = None,
+        /// Audio sample rate, in Hz
+        sample_rate: Option<u32> = None,
+        /// Container format (`mp4`, `mkv`, or `webm`), guessed from filename if not set
+        container: Option<Container> = None,
+    }
+
+    #[derive(Debug)]
+    pub struct Pause: Default {
+        /// Silence after title of post, in seconds
+        after_title: f32 = 0.8,
+        /// Silence between comments, or between body of post and comment, in seconds
+        between_comments: f32 = 0.5,
+        /// Silence before title of next post, in seconds
+        between_posts: f32 = 1.0,
+        /// Sound to play in pause before each comment
+        transition: Option<String> = None,
+    }
+
+    #[derive(Debug)]
+    pub struct Preview: Default {
+        /// Output filename of preview
+        name: String = "preview.mp4",
+        /// Fraction of output resolution to render at
+        scale: f32 = 0.5,
+        /// Frames per second
+        fps: f32 = 15.0,
+        /// Only render this many text frames from start, without outro
+        frames: Option<usize> = None,
+        /// Start of time window to render, in seconds
+        start: Option<f32> = None,
+        /// Length of time window to render, in seconds
+        length: Option<f32> = None,
+        /// Indices of text frames to save still image of, as PNG next to preview
+        thumbnails: Vec<usize> = Default,
     }
 
     #[derive(Debug)]
     pub struct Reddit: Default {
-        subreddit: String = "askreddit",
-        sort: String = "top",
-        time: String = "month",
+        /// Subreddit, multireddit (`a+b+c`), or list of subreddits, each as name or table with
+        /// `name` and its own `sort`, `time`, `limit`, and `weight`
+        subreddit: Subreddits = "askreddit",
+        /// Sort of posts (`hot`, `new`, `top`, `rising`, `controversial`, or `best`, which is `hot`
+        /// for a subreddit)
+        sort: Sort = Default,
+        /// Time period of `top` and `controversial` posts (`hour`, `day`, `week`, `month`,
+        /// `year`, or `all`)
+        time: Time = Default,
+        /// How to combine posts of multiple subreddits (`interleave`, `score`, or `random`,
+        /// weighted by `weight` of each subreddit)
+        merge: Merge = Default,
         comments: bool = true,
+        /// Sort of comments (`top`, `best`, `controversial`, `new`, or `qa`)
+        comment_sort: CommentSort = Default,
+        /// Id of post to take comments from, instead of choosing from list
+        post: Option<String> = None,
+        /// How to choose post to take comments from (`prompt`, or `first` to run unattended)
+        choose: ChoosePost = Default,
+        /// Ids of posts to skip, such as posts used in previous videos
+        exclude: Vec<String> = Default,
         limit: usize = 500usize,
     }
 
+    #[derive(Debug)]
+    pub struct Thumbnail: Default {
+        /// Create thumbnail image next to each video
+        enabled: bool = false,
+        /// Image format of thumbnail
+        format: ImageFormat = Default,
+        /// Path to TrueType or OpenType font file, or font of cards if not set
+        font: Option<String> = None,
+    }
+
     #[derive(Debug)]
     pub struct Voice: Default {
         language: String = "en-GB",
@@ -40,9 +245,208 @@ destructs! {
     }
 }
 
+/// Format of saved image
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    Jpeg,
+}
+
+impl ImageFormat {
+    /// File extension of format
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+        }
+    }
+}
+
+/// Format of metadata file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetadataFormat {
+    #[default]
+    Json,
+    Yaml,
+}
+
+/// File or directory, or list of either
+#[derive(Debug, serde::Deserialize)]
+#[serde(untagged, expecting = "a path, or list of paths")]
+pub enum MediaSource {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl MediaSource {
+    /// List of paths (files or directories)
+    pub fn paths(&self) -> Vec<&str> {
+        match self {
+            Self::One(path) => vec![path],
+            Self::Many(paths) => paths.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+impl From<&str> for MediaSource {
+    fn from(path: &str) -> Self {
+        Self::One(path.to_string())
+    }
+}
+
+/// Subreddit, or list of subreddits
+#[derive(Debug)]
+pub enum Subreddits {
+    One(String),
+    Many(Vec<SubredditSource>),
+}
+
+// Not untagged, so errors of settings in list are not hidden behind a generic error
+impl<'de> serde::Deserialize<'de> for Subreddits {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = Subreddits;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str(
+                    "a subreddit name, or list of names or tables like `{ name = \"jokes\", sort = \"new\" }`",
+                )
+            }
+
+            fn visit_str<E: serde::de::Error>(self, name: &str) -> Result<Self::Value, E> {
+                Ok(Subreddits::One(name.to_string()))
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut sources = Vec::new();
+                while let Some(source) = seq.next_element()? {
+                    sources.push(source);
+                }
+                Ok(Subreddits::Many(sources))
+            }
+        }
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+impl Subreddits {
+    /// Subreddits, each with its own settings
+    pub fn sources(&self) -> Vec<SubredditSettings> {
+        match self {
+            Self::One(name) => vec![SubredditSettings::new(name)],
+            Self::Many(sources) => sources
+                .iter()
+                .map(|source| match source {
+                    SubredditSource::Name(name) => SubredditSettings::new(name),
+                    SubredditSource::Settings(settings) => settings.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<&str> for Subreddits {
+    fn from(name: &str) -> Self {
+        Self::One(name.to_string())
+    }
+}
+
+impl std::fmt::Display for Subreddits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<_> = self
+            .sources()
+            .into_iter()
+            .map(|source| format!("r/{}", source.name))
+            .collect();
+        write!(f, "{}", names.join(", "))
+    }
+}
+
+/// Subreddit in list, as name or table with settings
+#[derive(Debug)]
+pub enum SubredditSource {
+    Name(String),
+    Settings(SubredditSettings),
+}
+
+impl<'de> serde::Deserialize<'de> for SubredditSource {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = SubredditSource;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str(
+                    "a subreddit name, or table with `name`, and optional `sort`, `time`, `limit`, and `weight`",
+                )
+            }
+
+            fn visit_str<E: serde::de::Error>(self, name: &str) -> Result<Self::Value, E> {
+                Ok(SubredditSource::Name(name.to_string()))
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                map: A,
+            ) -> Result<Self::Value, A::Error> {
+                let deserializer = serde::de::value::MapAccessDeserializer::new(map);
+                serde::Deserialize::deserialize(deserializer).map(SubredditSource::Settings)
+            }
+        }
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// Subreddit with settings, which override settings of `[reddit]` if set
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SubredditSettings {
+    /// Subreddit or multireddit name, without `r/`
+    pub name: String,
+    pub sort: Option<Sort>,
+    pub time: Option<Time>,
+    /// Amount of posts to fetch
+    pub limit: Option<usize>,
+    /// Share of posts, relative to other subreddits, when merging randomly
+    #[serde(default = "default_weight")]
+    pub weight: f32,
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+impl SubredditSettings {
+    /// Subreddit with default settings
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            sort: None,
+            time: None,
+            limit: None,
+            weight: default_weight(),
+        }
+    }
+}
+
 impl std::str::FromStr for Config {
     type Err = toml::de::Error;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        toml::from_str(s)
+        let mut config: Self = toml::from_str(s)?;
+        if let Some(overwrite) = config.out.overwrite {
+            config.out.collision = if overwrite {
+                Collision::Overwrite
+            } else {
+                Collision::Error
+            };
+        }
+        Ok(config)
     }
 }
@@ -0,0 +1,361 @@
+/// Parse cron-like schedules
+mod schedule;
+
+use chrono::{Local, TimeDelta, Timelike};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use crate::{
+    batch::{self, BatchJob},
+    config::Config,
+    pipeline::Options,
+    reddit::ChoosePost,
+    video::Collision,
+};
+
+pub use self::schedule::Schedule;
+
+/// Settings of daemon, with scheduled jobs, parsed from toml file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Daemon {
+    /// Folder to move finished videos into, unless job sets `dir`
+    #[serde(default = "default_drop_dir")]
+    pub drop_dir: String,
+    /// File of posts which were already used, so new videos use fresh posts
+    #[serde(default = "default_history")]
+    pub history: String,
+    /// File to append log of each run to
+    #[serde(default = "default_log")]
+    pub log: String,
+    /// Jobs as `[[job]]` tables, like batch file, each with a `schedule`
+    #[serde(rename = "job", default)]
+    pub jobs: Vec<ScheduledJob>,
+}
+
+/// Job of daemon, with settings of batch job and when to run it
+#[derive(Debug)]
+pub struct ScheduledJob {
+    /// When to run job, as cron expression (`minute hour day month weekday`)
+    pub schedule: Option<String>,
+    pub job: BatchJob,
+}
+
+// Flattening `BatchJob` would not reject unknown fields, so `schedule` is taken out of table first
+impl<'de> Deserialize<'de> for ScheduledJob {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut table = toml::Table::deserialize(deserializer)?;
+        let schedule = match table.remove("schedule") {
+            Some(toml::Value::String(schedule)) => Some(schedule),
+            Some(_) => return Err(serde::de::Error::custom("`schedule` must be a string")),
+            None => None,
+        };
+        let job = toml::Value::Table(table)
+            .try_into()
+            .map_err(serde::de::Error::custom)?;
+        Ok(Self { schedule, job })
+    }
+}
+
+fn default_drop_dir() -> String {
+    "drop".to_string()
+}
+fn default_history() -> String {
+    "history.json".to_string()
+}
+fn default_log() -> String {
+    "daemon.log".to_string()
+}
+
+impl std::str::FromStr for Daemon {
+    type Err = toml::de::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s)
+    }
+}
+
+/// Post used in a previous video
+#[derive(Debug, Serialize, Deserialize)]
+struct HistoryEntry {
+    post_id: String,
+    /// Name of job
+    job: String,
+    /// Local time of run
+    time: String,
+}
+
+/// Run scheduled jobs forever, checking schedules at the start of every minute
+///
+/// Jobs run one at a time, with each job using config parsed from `config_text`. A job which is
+/// due while another is running is skipped until it is next due. Returns only if a schedule is
+/// invalid.
+pub fn run_daemon(config_text: &str, daemon: &Daemon, options: &Options) -> Result<(), String> {
+    let mut jobs = Vec::new();
+    for (i, ScheduledJob { schedule, job }) in daemon.jobs.iter().enumerate() {
+        let name = job.name(i);
+        let schedule: Schedule = schedule
+            .as_deref()
+            .ok_or_else(|| format!("Job '{name}' has no schedule"))?
+            .parse()?;
+        jobs.push((name, job, schedule));
+    }
+
+    log(
+        daemon,
+        "daemon",
+        &format!("Started with {} jobs", jobs.len()),
+    );
+
+    loop {
+        // Wait until start of next minute
+        let now = Local::now();
+        let next = (now + TimeDelta::minutes(1))
+            .with_second(0)
+            .and_then(|time| time.with_nanosecond(0))
+            .expect("Failed to get start of minute");
+        thread::sleep((next - now).to_std().unwrap_or_default());
+
+        for (name, job, schedule) in &jobs {
+            if schedule.matches(&next) {
+                run_scheduled(config_text, daemon, name, job, options);
+            }
+        }
+    }
+}
+
+/// Run one scheduled job, skipping used posts and moving finished files to drop folder
+fn run_scheduled(
+    config_text: &str,
+    daemon: &Daemon,
+    name: &str,
+    job: &BatchJob,
+    options: &Options,
+) {
+    log(daemon, name, "Started");
+
+    let mut history = load_history(&daemon.history);
+    let drop_dir = job.dir.clone().unwrap_or_else(|| daemon.drop_dir.clone());
+    // Files are rendered next to drop folder, so only finished files appear in it
+    let staging_dir = format!("{drop_dir}/.rendering");
+    let mut collision = Collision::default();
+
+    let result = config_text
+        .parse::<Config>()
+        .map_err(|err| format!("Failed to parse config file - {err}"))
+        .and_then(|mut config| {
+            // Nobody is there to choose a post
+            config.reddit.choose = ChoosePost::First;
+            job.apply(&mut config)?;
+
+            let used = history.iter().map(|entry| entry.post_id.clone());
+            config.reddit.exclude.extend(used);
+            config.out.dir = staging_dir.clone();
+            // Runs are recorded in log instead
+            config.out.manifest = false;
+            collision = config.out.collision;
+
+            // Fixed post is not fetched from a feed, which would skip it
+            let reddit = &config.reddit;
+            if let Some(post) = reddit
+                .post
+                .as_ref()
+                .filter(|id| reddit.exclude.contains(id))
+            {
+                log(
+                    daemon,
+                    name,
+                    &format!("Post '{post}' was already used, skipping"),
+                );
+                return Ok(None);
+            }
+
+            batch::run_isolated(&config, options).map(Some)
+        });
+
+    let summary = match result {
+        Ok(Some(summary)) => summary,
+        Ok(None) => return,
+        Err(err) => {
+            log(daemon, name, &format!("Failed - {err}"));
+            return;
+        }
+    };
+
+    if let Err(err) = fs::create_dir_all(&drop_dir) {
+        log(
+            daemon,
+            name,
+            &format!("Failed to create drop folder - {err}"),
+        );
+        return;
+    }
+    let mut files = Vec::new();
+    match drop_paths(&summary.files, &drop_dir, collision) {
+        Ok(Some(dests)) => {
+            for (file, dest) in summary.files.iter().zip(dests) {
+                match fs::rename(file, &dest) {
+                    Ok(()) => files.push(dest.to_string_lossy().to_string()),
+                    Err(err) => log(daemon, name, &format!("Failed to move '{file}' - {err}")),
+                }
+            }
+        }
+        Ok(None) => {
+            log(daemon, name, "Files already exist in drop folder, skipping");
+            for file in &summary.files {
+                let _ = fs::remove_file(file);
+            }
+        }
+        // Files are kept in staging folder
+        Err(err) => log(daemon, name, &format!("Failed to move files - {err}")),
+    }
+
+    // Posts are only used if their video reached the drop folder, not in dry run
+    let posts = if options.dry_run || files.is_empty() {
+        Vec::new()
+    } else {
+        summary.posts
+    };
+    let time = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    for post_id in posts {
+        history.push(HistoryEntry {
+            post_id,
+            job: name.to_string(),
+            time: time.clone(),
+        });
+    }
+    if let Err(err) = save_history(&daemon.history, &history) {
+        log(daemon, name, &err);
+    }
+
+    let files = files.join(", ");
+    log(daemon, name, &format!("Finished, saved {files}"));
+}
+
+/// Path in drop folder of each file, resolved with collision policy of config
+///
+/// Files of one run are renamed together, with the same number added before the first dot of
+/// each filename, so files saved next to a video keep its name. Returns `None` if files exist
+/// and should be skipped.
+fn drop_paths(
+    files: &[String],
+    drop_dir: &str,
+    collision: Collision,
+) -> Result<Option<Vec<PathBuf>>, String> {
+    let paths = |suffix: &str| -> Vec<PathBuf> {
+        files
+            .iter()
+            .map(|file| {
+                let filename = Path::new(file)
+                    .file_name()
+                    .map(|filename| filename.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let filename = match filename.split_once('.') {
+                    Some((stem, extension)) => format!("{stem}{suffix}.{extension}"),
+                    None => format!("{filename}{suffix}"),
+                };
+                Path::new(drop_dir).join(filename)
+            })
+            .collect()
+    };
+
+    let dests = paths("");
+    let Some(existing) = dests.iter().find(|dest| dest.exists()) else {
+        return Ok(Some(dests));
+    };
+    match collision {
+        Collision::Overwrite => Ok(Some(dests)),
+        Collision::Skip => Ok(None),
+        Collision::Error => Err(format!("'{}' already exists", existing.display())),
+        Collision::Rename => {
+            let mut i = 1;
+            loop {
+                let dests = paths(&format!("-{i}"));
+                if !dests.iter().any(|dest| dest.exists()) {
+                    return Ok(Some(dests));
+                }
+                i += 1;
+            }
+        }
+    }
+}
+
+/// Load history of used posts, or empty history if file does not exist
+fn load_history(path: &str) -> Vec<HistoryEntry> {
+    match fs::read_to_string(path) {
+        Ok(text) => serde_json::from_str(&text).unwrap_or_else(|err| {
+            eprintln!("[warning] Failed to parse history '{path}' - {err}");
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn save_history(path: &str, history: &[HistoryEntry]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(history).expect("Failed to serialize history");
+    fs::write(path, json).map_err(|err| format!("Failed to save history '{path}' - {err}"))
+}
+
+/// Print message, and append it to log file with time and name of job
+fn log(daemon: &Daemon, name: &str, message: &str) {
+    let time = Local::now().format("%Y-%m-%d %H:%M:%S");
+    let line = format!("{time} [{name}] {message}");
+    println!("{line}");
+
+    let result = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&daemon.log)
+        .and_then(|mut file| writeln!(file, "{line}"));
+    if let Err(err) = result {
+        eprintln!("[warning] Failed to write log '{}' - {err}", daemon.log);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jobs_have_schedule_and_batch_settings() {
+        let daemon: Daemon = r#"
+            [[job]]
+            subreddit = "jokes"
+            schedule = "@daily"
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(daemon.jobs[0].schedule.as_deref(), Some("@daily"));
+        assert_eq!(daemon.jobs[0].job.name(0), "r/jokes");
+
+        // Unknown fields of job are still rejected
+        let typo = "[[job]]\nsubredit = \"jokes\"\nschedule = \"@daily\"";
+        let err = typo.parse::<Daemon>().unwrap_err().to_string();
+        assert!(err.contains("subredit"), "{err}");
+    }
+
+    #[test]
+    fn drop_paths_follow_collision_policy() {
+        let dir = std::env::temp_dir().join(format!("reddit-video-drop-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("video.mp4"), "").unwrap();
+        let drop_dir = dir.to_string_lossy();
+
+        let files = ["staging/video.mp4", "staging/video.thumbnail.png"].map(String::from);
+        let dests = drop_paths(&files, &drop_dir, Collision::Rename)
+            .unwrap()
+            .unwrap();
+        assert_eq!(dests[0], dir.join("video-1.mp4"));
+        assert_eq!(dests[1], dir.join("video-1.thumbnail.png"));
+        assert!(drop_paths(&files, &drop_dir, Collision::Skip)
+            .unwrap()
+            .is_none());
+        assert!(drop_paths(&files, &drop_dir, Collision::Error).is_err());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,151 @@
+use chrono::{DateTime, Datelike, Local, Timelike};
+use std::str::FromStr;
+
+/// Cron-like schedule of when a job runs, parsed from `minute hour day month weekday`
+///
+/// Fields can be `*`, numbers, ranges (`1-5`), lists (`1,15`), and steps (`*/15`). Weekdays are
+/// `0` to `7`, where both `0` and `7` are Sunday. Aliases `@hourly`, `@daily`, `@weekly`, and
+/// `@monthly` are also accepted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    /// Bit set of each field
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Whether day or weekday fields are `*`, for cron rule of matching either day field
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Schedule {
+    /// Whether job runs in minute of time
+    pub fn matches(&self, time: &DateTime<Local>) -> bool {
+        let has = |set: u64, value: u32| set & (1 << value) != 0;
+
+        let day = has(self.days, time.day());
+        let weekday = has(self.weekdays, time.weekday().num_days_from_sunday());
+        // Like cron, if both day fields are restricted, either can match
+        let day_matches = if self.any_day || self.any_weekday {
+            day && weekday
+        } else {
+            day || weekday
+        };
+
+        has(self.minutes, time.minute())
+            && has(self.hours, time.hour())
+            && has(self.months, time.month())
+            && day_matches
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expression = match s.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            expression => expression,
+        };
+
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!(
+                "Schedule '{s}' must have 5 fields: minute hour day month weekday"
+            ));
+        };
+
+        let mut weekdays_set = parse_field(weekdays, 0, 7)?;
+        // Both 0 and 7 are Sunday
+        if weekdays_set & (1 << 7) != 0 {
+            weekdays_set |= 1;
+        }
+
+        Ok(Self {
+            minutes: parse_field(minutes, 0, 59)?,
+            hours: parse_field(hours, 0, 23)?,
+            days: parse_field(days, 1, 31)?,
+            months: parse_field(months, 1, 12)?,
+            weekdays: weekdays_set,
+            any_day: days == "*",
+            any_weekday: weekdays == "*",
+        })
+    }
+}
+
+/// Parse field of schedule into bit set of allowed values
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let parse = |value: &str| {
+        value
+            .parse::<u32>()
+            .ok()
+            .filter(|value| (min..=max).contains(value))
+            .ok_or_else(|| format!("Invalid value '{value}' in schedule, expected {min}-{max}"))
+    };
+
+    let mut set = 0;
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, Some(step)),
+                _ => return Err(format!("Invalid step '{step}' in schedule")),
+            },
+            None => (item, None),
+        };
+
+        let (start, end) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            // Single value with step continues to end, like `5/15`
+            None if step.is_some() => (parse(range)?, max),
+            None => {
+                let value = parse(range)?;
+                (value, value)
+            }
+        };
+        // Range would otherwise match nothing, and job would never run
+        if start > end {
+            return Err(format!(
+                "Invalid range '{range}' in schedule, start is after end"
+            ));
+        }
+
+        for value in (start..=end).step_by(step.unwrap_or(1) as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn schedules_match_time() {
+        // Monday
+        let time = Local.with_ymd_and_hms(2024, 1, 15, 9, 30, 0).unwrap();
+
+        let matches = |s: &str| s.parse::<Schedule>().unwrap().matches(&time);
+        assert!(matches("* * * * *"));
+        assert!(matches("30 9 * * *"));
+        assert!(matches("*/15 8-10 * * 1-5"));
+        assert!(matches("0,30 9 15 1 *"));
+        assert!(!matches("0 9 * * *"));
+        assert!(!matches("30 9 * * 0,6"));
+        // Either day field matches, if both are restricted
+        assert!(matches("30 9 1 * 1"));
+        assert!(!matches("@daily"));
+
+        assert!("* * *".parse::<Schedule>().is_err());
+        assert!("60 * * * *".parse::<Schedule>().is_err());
+        assert!("*/0 * * * *".parse::<Schedule>().is_err());
+        assert!("0-30/0 * * * *".parse::<Schedule>().is_err());
+        assert!("30-10 * * * *".parse::<Schedule>().is_err());
+        assert!("0 9 * * 5-1".parse::<Schedule>().is_err());
+    }
+}
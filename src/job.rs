@@ -0,0 +1,548 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    io::ErrorKind,
+    path::Path,
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, SystemTime},
+};
+
+use crate::{config, reddit::Post, voice::Voice, Text};
+
+/// Filename of checkpoint, in job directory
+const CHECKPOINT_NAME: &str = "checkpoint.json";
+/// Filename of lockfile, in job directory, which exists while a run is using the job
+const LOCK_NAME: &str = "job.lock";
+
+/// When to remove job directory, after run finishes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Cleanup {
+    /// Remove after successful run, and keep after failure to inspect or resume
+    #[default]
+    Success,
+    /// Always remove, even after failure
+    Always,
+    /// Always keep
+    Never,
+}
+
+/// Stage of pipeline, in order
+///
+/// Concatenating voices and muxing audio with video are part of rendering, which is a single
+/// ffmpeg pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stage {
+    /// Posts were fetched from Reddit
+    Fetch,
+    /// Texts were selected from posts, or comments of chosen post
+    Select,
+    /// Voices were fetched for all texts
+    Synthesize,
+    /// All parts of video were rendered
+    Render,
+}
+
+/// Progress of job, saved after each stage
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Checkpoint {
+    /// Last completed stage
+    stage: Option<Stage>,
+    /// Seed of random number generator, so background and music choices are repeated
+    seed: Option<u64>,
+    /// Indexes of parts which were rendered
+    rendered: Vec<usize>,
+}
+
+/// Voice, saved without its audio
+#[derive(Serialize, Deserialize)]
+struct VoiceRecord {
+    text: Text,
+    /// Duration of speech, in milliseconds
+    duration: u64,
+    /// Whether voice has an audio file
+    audio: bool,
+}
+
+/// Working directory of one run, with checkpoint of each completed stage
+///
+/// Each job has its own directory, which is locked while in use, so concurrent runs do not
+/// clobber each other
+#[derive(Debug)]
+pub struct Job {
+    /// Unique name of job, and its directory
+    pub id: String,
+    /// Path of directory
+    pub dir: String,
+    checkpoint: Checkpoint,
+}
+
+impl Job {
+    /// Start new job in a new, unique directory
+    pub fn create(config: &config::Out) -> Result<Self, String> {
+        let jobs_dir = jobs_dir(config);
+        fs::create_dir_all(&jobs_dir)
+            .map_err(|err| format!("Failed to create job directory '{jobs_dir}' - {err}"))?;
+
+        // Process id keeps directories unique, if runs start in the same second, and a counter
+        // is added for jobs of the same process
+        let time = chrono::Local::now().format("%Y%m%d-%H%M%S");
+        let pid = process::id();
+        let mut i = 0;
+        let (id, dir) = loop {
+            let id = match i {
+                0 => format!("{time}-{pid}"),
+                _ => format!("{time}-{pid}-{i}"),
+            };
+            let dir = format!("{jobs_dir}/{id}");
+            match fs::create_dir(&dir) {
+                Ok(()) => break (id, dir),
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => i += 1,
+                Err(err) => return Err(format!("Failed to create job directory '{dir}' - {err}")),
+            }
+        };
+        lock(&dir, &id)?;
+
+        Ok(Self {
+            id,
+            dir,
+            checkpoint: Checkpoint::default(),
+        })
+    }
+
+    /// Resume job from its checkpoint, or latest job which is not in use if no id is given
+    pub fn resume(config: &config::Out, id: Option<&str>) -> Result<Self, String> {
+        let id = match id {
+            Some(id) => id.to_string(),
+            None => latest_job(config)?,
+        };
+        let dir = format!("{}/{id}", jobs_dir(config));
+
+        let path = format!("{dir}/{CHECKPOINT_NAME}");
+        let text = fs::read_to_string(&path)
+            .map_err(|err| format!("No job to resume in '{dir}' - {err}"))?;
+        let checkpoint = serde_json::from_str(&text)
+            .map_err(|err| format!("Failed to parse checkpoint '{path}' - {err}"))?;
+        lock(&dir, &id)?;
+
+        Ok(Self {
+            id,
+            dir,
+            checkpoint,
+        })
+    }
+
+    /// Finish run, and remove directory according to cleanup policy
+    ///
+    /// Lock is released when job is dropped, including on panic
+    pub fn close(self, config: &config::Out, success: bool) {
+        let remove = match config.cleanup {
+            Cleanup::Success => success,
+            Cleanup::Always => true,
+            Cleanup::Never => false,
+        };
+
+        if remove {
+            if let Err(err) = fs::remove_dir_all(&self.dir) {
+                eprintln!(
+                    "[warning] Failed to remove job directory '{}' - {err}",
+                    self.dir
+                );
+            }
+        } else if !success {
+            let Self { id, dir, .. } = &self;
+            println!("[info] Kept job directory '{dir}', to resume run `--resume {id}`");
+        }
+    }
+
+    /// Whether stage was completed
+    pub fn completed(&self, stage: Stage) -> bool {
+        self.checkpoint.stage >= Some(stage)
+    }
+
+    /// Last completed stage
+    pub fn stage(&self) -> Option<Stage> {
+        self.checkpoint.stage
+    }
+
+    /// Seed of random number generator, if saved
+    pub fn seed(&self) -> Option<u64> {
+        self.checkpoint.seed
+    }
+
+    /// Save seed of random number generator
+    pub fn set_seed(&mut self, seed: u64) {
+        self.checkpoint.seed = Some(seed);
+        self.save_checkpoint();
+    }
+
+    /// Save fetched posts, and complete fetch stage
+    pub fn save_posts(&mut self, posts: &[Post]) {
+        self.save_json("posts.json", posts);
+        self.complete(Stage::Fetch);
+    }
+
+    /// Load posts saved by fetch stage
+    pub fn load_posts(&self) -> Vec<Post> {
+        self.load_json("posts.json")
+    }
+
+    /// Save selected texts, and complete select stage
+    pub fn save_texts(&mut self, texts: &[Text]) {
+        self.save_json("texts.json", texts);
+        self.complete(Stage::Select);
+    }
+
+    /// Load texts saved by select stage
+    pub fn load_texts(&self) -> Vec<Text> {
+        self.load_json("texts.json")
+    }
+
+    /// Save voices with their audio, and complete synthesize stage
+    pub fn save_voices(&mut self, voices: &[Voice]) {
+        fs::create_dir_all(format!("{}/voices", self.dir)).expect("Failed to create voices dir");
+
+        let mut records = Vec::new();
+        for (i, voice) in voices.iter().enumerate() {
+            let audio = !voice.bytes.is_empty();
+            if audio {
+                fs::write(format!("{}/voices/{i}.mp3", self.dir), &voice.bytes)
+                    .expect("Failed to save voice file");
+            }
+            records.push(VoiceRecord {
+                text: voice.text.clone(),
+                duration: voice.duration.as_millis() as u64,
+                audio,
+            });
+        }
+
+        self.save_json("voices.json", &records);
+        self.complete(Stage::Synthesize);
+    }
+
+    /// Load voices with their audio, saved by synthesize stage
+    pub fn load_voices(&self) -> Vec<Voice> {
+        let records: Vec<VoiceRecord> = self.load_json("voices.json");
+        records
+            .into_iter()
+            .enumerate()
+            .map(|(i, record)| Voice {
+                text: record.text,
+                bytes: if record.audio {
+                    fs::read(format!("{}/voices/{i}.mp3", self.dir))
+                        .expect("Failed to read saved voice file")
+                } else {
+                    Vec::new()
+                },
+                duration: Duration::from_millis(record.duration),
+                estimated: false,
+            })
+            .collect()
+    }
+
+    /// Whether part was rendered
+    pub fn is_rendered(&self, part_index: usize) -> bool {
+        self.checkpoint.rendered.contains(&part_index)
+    }
+
+    /// Record that part was rendered, and complete render stage if all parts are rendered
+    pub fn complete_part(&mut self, part_index: usize, part_count: usize) {
+        if !self.is_rendered(part_index) {
+            self.checkpoint.rendered.push(part_index);
+        }
+        if self.checkpoint.rendered.len() >= part_count {
+            self.complete(Stage::Render);
+        } else {
+            self.save_checkpoint();
+        }
+    }
+
+    /// Set last completed stage, and save checkpoint
+    fn complete(&mut self, stage: Stage) {
+        self.checkpoint.stage = Some(stage);
+        self.save_checkpoint();
+    }
+
+    fn save_checkpoint(&self) {
+        self.save_json(CHECKPOINT_NAME, &self.checkpoint);
+    }
+
+    fn save_json<T: Serialize + ?Sized>(&self, name: &str, value: &T) {
+        let json = serde_json::to_string_pretty(value).expect("Failed to serialize checkpoint");
+        fs::write(format!("{}/{name}", self.dir), json).expect("Failed to save checkpoint");
+    }
+
+    fn load_json<T: for<'de> Deserialize<'de>>(&self, name: &str) -> T {
+        let text = fs::read_to_string(format!("{}/{name}", self.dir))
+            .expect("Failed to read checkpoint file");
+        serde_json::from_str(&text).expect("Failed to parse checkpoint file")
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        // Directory may have been removed already
+        let _ = fs::remove_file(format!("{}/{LOCK_NAME}", self.dir));
+    }
+}
+
+/// Directory containing all job directories
+pub fn jobs_dir(config: &config::Out) -> String {
+    match &config.work_dir {
+        Some(dir) => dir.clone(),
+        None => {
+            let temp = env::temp_dir().to_string_lossy().to_string();
+            let name = env!("CARGO_PKG_NAME");
+            format!("{temp}/{name}")
+        }
+    }
+}
+
+/// Create lockfile in job directory, containing process id
+///
+/// Lockfile of a process which is no longer running, such as a killed run, is taken over
+fn lock(dir: &str, id: &str) -> Result<(), String> {
+    // Lockfile is written in full before it appears, by linking it from a temporary file
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let pid = process::id();
+    let temp = format!(
+        "{dir}/{LOCK_NAME}.{pid}-{}",
+        COUNT.fetch_add(1, Ordering::SeqCst)
+    );
+    fs::write(&temp, pid.to_string())
+        .map_err(|err| format!("Failed to lock job '{id}' - {err}"))?;
+    let result = acquire_lock(dir, id, &temp);
+    let _ = fs::remove_file(&temp);
+    result
+}
+
+/// Link lockfile from temporary file, taking over stale lockfile
+fn acquire_lock(dir: &str, id: &str, temp: &str) -> Result<(), String> {
+    let path = format!("{dir}/{LOCK_NAME}");
+    let in_use = |owner: Option<u32>| {
+        let owner = owner.map_or("unknown".to_string(), |pid| pid.to_string());
+        format!(
+            "Job '{id}' is in use by process {owner}. \
+            If that run was killed, remove '{path}' and try again"
+        )
+    };
+
+    loop {
+        match fs::hard_link(temp, &path) {
+            Ok(()) => break,
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => {}
+            Err(err) => return Err(format!("Failed to lock job '{id}' - {err}")),
+        }
+        if is_locked(Path::new(dir)) {
+            return Err(in_use(lock_owner(&path)));
+        }
+
+        // Stale lockfile is moved away, which only one process can do
+        let stale = format!("{temp}.stale");
+        match fs::rename(&path, &stale) {
+            Ok(()) => {}
+            // Moved by another process
+            Err(err) if err.kind() == ErrorKind::NotFound => continue,
+            Err(err) => return Err(format!("Failed to lock job '{id}' - {err}")),
+        }
+        // Another process may have taken over the lock in the meantime, so it is put back
+        let owner = lock_owner(&stale);
+        if owner.is_some_and(is_running) {
+            let _ = fs::hard_link(&stale, &path);
+            let _ = fs::remove_file(&stale);
+            return Err(in_use(owner));
+        }
+        let _ = fs::remove_file(&stale);
+        println!("[info] Taking over lock of job '{id}' from stopped process");
+    }
+
+    // Confirm that lock was not taken over by another process
+    let owner = lock_owner(&path);
+    if owner != Some(process::id()) {
+        return Err(in_use(owner));
+    }
+    Ok(())
+}
+
+/// Id of process which created lockfile, if it is valid
+fn lock_owner(path: &str) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Whether job directory is locked by a run which is still running
+///
+/// Lockfile is always written in full, so a lockfile without a valid process id is stale
+fn is_locked(dir: &Path) -> bool {
+    lock_owner(&dir.join(LOCK_NAME).to_string_lossy()).is_some_and(is_running)
+}
+
+/// Whether process is running
+///
+/// Checked with `/proc` on Linux, and `kill -0` on other Unix systems. Elsewhere, process is
+/// assumed to be running.
+fn is_running(pid: u32) -> bool {
+    if cfg!(target_os = "linux") {
+        Path::new(&format!("/proc/{pid}")).exists()
+    } else if cfg!(unix) {
+        process::Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .stderr(process::Stdio::null())
+            .status()
+            .map_or(true, |status| status.success())
+    } else {
+        true
+    }
+}
+
+/// Id of newest job which has a checkpoint and is not in use
+fn latest_job(config: &config::Out) -> Result<String, String> {
+    let dir = jobs_dir(config);
+    let entries =
+        fs::read_dir(&dir).map_err(|err| format!("No jobs to resume in '{dir}' - {err}"))?;
+
+    // Ids start with time, so newest job sorts last
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.join(CHECKPOINT_NAME).exists() && !is_locked(path))
+        .filter_map(|path| Some(path.file_name()?.to_string_lossy().to_string()))
+        .max()
+        .ok_or_else(|| format!("No jobs to resume in '{dir}'"))
+}
+
+/// Remove job directories which are older than configured maximum age, and not in use
+///
+/// Only directories with a checkpoint, or named like a job id, are removed, as work directory
+/// may contain other files. Returns amount of directories removed.
+pub fn remove_stale_jobs(config: &config::Out) -> Result<usize, String> {
+    let Some(max_age) = config.max_job_age else {
+        return Ok(0);
+    };
+    let max_age = Duration::try_from_secs_f32(max_age * 60.0 * 60.0)
+        .map_err(|_| format!("`max_job_age` must be a positive number of hours, not {max_age}"))?;
+    let Ok(entries) = fs::read_dir(jobs_dir(config)) else {
+        return Ok(0);
+    };
+
+    let mut count = 0;
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if !path.is_dir() || !is_job_dir(&path) || is_locked(&path) {
+            continue;
+        }
+        // Writing files inside does not update time of directory itself
+        let age = last_modified(&path)
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok());
+        if age.is_some_and(|age| age > max_age) && fs::remove_dir_all(&path).is_ok() {
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Latest time that directory, or any file within it, was modified
+fn last_modified(path: &Path) -> Option<SystemTime> {
+    let modified = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok();
+    let Ok(entries) = fs::read_dir(path) else {
+        return modified;
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| last_modified(&entry.path()))
+        .chain(modified)
+        .max()
+}
+
+/// Whether directory was created by a job
+fn is_job_dir(dir: &Path) -> bool {
+    // Time and process id, with counter for later jobs of same process
+    let id_pattern = Regex::new(r"^\d{8}-\d{6}-\d+(-\d+)?$").unwrap();
+    let is_id = dir
+        .file_name()
+        .is_some_and(|name| id_pattern.is_match(&name.to_string_lossy()));
+    is_id || dir.join(CHECKPOINT_NAME).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn completed_stages_are_ordered() {
+        let mut job = Job {
+            id: String::new(),
+            dir: String::new(),
+            checkpoint: Checkpoint::default(),
+        };
+        assert!(!job.completed(Stage::Fetch));
+
+        job.checkpoint.stage = Some(Stage::Select);
+        assert!(job.completed(Stage::Fetch));
+        assert!(job.completed(Stage::Select));
+        assert!(!job.completed(Stage::Synthesize));
+        assert!(!job.completed(Stage::Render));
+    }
+
+    #[test]
+    fn jobs_are_locked_and_resumed() {
+        let mut config = config::Out::default();
+        let dir = env::temp_dir().join(format!("reddit-video-test-{}", process::id()));
+        config.work_dir = Some(dir.to_string_lossy().to_string());
+
+        let mut job = Job::create(&config).unwrap();
+        assert!(!job.completed(Stage::Fetch));
+        job.save_texts(&[]);
+        assert!(job.completed(Stage::Fetch));
+        assert!(!job.completed(Stage::Synthesize));
+
+        // Locked while in use
+        assert!(Job::resume(&config, Some(&job.id)).is_err());
+        let id = job.id.clone();
+        drop(job);
+
+        // Lock of stopped process, or without process id, is stale
+        let lock_path = format!("{}/{id}/{LOCK_NAME}", dir.to_string_lossy());
+        let job_dir = Path::new(&lock_path).parent().unwrap();
+        fs::write(&lock_path, "").unwrap();
+        assert!(!is_locked(job_dir));
+        fs::write(&lock_path, u32::MAX.to_string()).unwrap();
+        assert!(!is_locked(job_dir));
+
+        let job = Job::resume(&config, None).unwrap();
+        assert_eq!(job.id, id);
+        assert_eq!(job.stage(), Some(Stage::Select));
+        job.close(&config, true);
+
+        assert!(Job::resume(&config, None).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn only_job_dirs_are_removed() {
+        assert!(is_job_dir(Path::new("/tmp/20240102-030405-1234")));
+        assert!(is_job_dir(Path::new("/tmp/20240102-030405-1234-2")));
+        assert!(!is_job_dir(Path::new("/tmp/reddit-video-missing-dir")));
+
+        let config = config::Out {
+            max_job_age: Some(-1.0),
+            ..Default::default()
+        };
+        assert!(remove_stale_jobs(&config).is_err());
+    }
+
+    #[test]
+    fn job_age_includes_files_within() {
+        let dir = env::temp_dir().join(format!("reddit-video-age-{}", process::id()));
+        fs::create_dir_all(dir.join("voices")).unwrap();
+        let dir_modified = fs::metadata(&dir).unwrap().modified().unwrap();
+
+        std::thread::sleep(Duration::from_millis(20));
+        fs::write(dir.join("voices").join("0.wav"), "").unwrap();
+        assert!(last_modified(&dir).unwrap() > dir_modified);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,5 +1,15 @@
+/// Render many videos from a list of jobs
+pub mod batch;
+/// Render text frames as card images
+pub mod card;
 /// Config, parsed from toml file
 pub mod config;
+/// Run jobs on schedules, with history of used posts
+pub mod daemon;
+/// Checkpoints of pipeline stages, to resume a failed run
+pub mod job;
+/// Run all stages, from fetching posts to rendering video
+pub mod pipeline;
 /// Reddit API fetching
 pub mod reddit;
 /// Render video with ffmpeg
@@ -7,12 +17,75 @@ pub mod video;
 /// Voice (TTS) API fetching
 pub mod voice;
 
-use std::{env, fs, path::Path};
+use serde::{Deserialize, Serialize};
+use std::fs;
 
 use self::voice::Voice;
 
-/// Text frames to render
-type Text = String;
+/// Text frame to render
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Text {
+    /// Where text came from, used for timing between frames
+    pub kind: TextKind,
+    /// Text to speak and display
+    pub content: String,
+    /// Post or comment which text came from
+    pub source: Option<Source>,
+}
+
+/// Post or comment which a text frame came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Source {
+    /// Subreddit name, without `r/`
+    pub subreddit: String,
+    /// Username of author
+    pub author: String,
+    /// Upvote score
+    pub score: u32,
+    /// Link to post or comment, in subreddit (`permalink`)
+    pub link: Option<String>,
+    /// Flair of post (`link_flair_text`)
+    pub flair: Option<String>,
+}
+
+/// Kind of text frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextKind {
+    /// Title of post
+    Title,
+    /// Body of post
+    Body,
+    /// Comment on post
+    Comment,
+    /// Introduction, showing title card of post
+    Intro,
+}
+
+impl Source {
+    /// Id of post, from link (`/r/{subreddit}/comments/{id}/...`)
+    pub fn post_id(&self) -> Option<&str> {
+        let mut segments = self.link.as_deref()?.split('/');
+        segments.position(|segment| segment == "comments")?;
+        segments.next().filter(|id| !id.is_empty())
+    }
+}
+
+impl Text {
+    /// Create text frame of given kind
+    pub fn new(kind: TextKind, content: impl Into<String>) -> Self {
+        Self {
+            kind,
+            content: content.into(),
+            source: None,
+        }
+    }
+
+    /// Set post or comment which text came from
+    pub fn with_source(mut self, source: Source) -> Self {
+        self.source = Some(source);
+        self
+    }
+}
 
 /// Convert a struct into a vector of text frames
 trait ToTexts {
@@ -21,46 +94,89 @@ trait ToTexts {
 }
 
 impl<T: ToTexts> ToTexts for Vec<T> {
-    fn to_texts(self) -> Vec<String> {
-        self.into_iter().map(ToTexts::to_texts).flatten().collect()
+    fn to_texts(self) -> Vec<Text> {
+        self.into_iter().flat_map(ToTexts::to_texts).collect()
     }
 }
 
 /// Fetch posts and comments, as texts
-pub fn fetch_posts_or_comments(config: &config::Reddit) -> Vec<Text> {
-    println!(
-        "Fetching {} posts of r/{}...",
-        reddit::sort_and_time(&config),
-        config.subreddit
-    );
+pub fn fetch_posts_or_comments(
+    config: &config::Reddit,
+    rng: &mut impl rand::Rng,
+) -> Result<Vec<Text>, String> {
+    select_texts(config, fetch_posts(config, rng)?)
+}
+
+/// Fetch and merge posts of subreddits, or only the configured post
+///
+/// `rng` is used to merge posts randomly, if configured
+pub fn fetch_posts(
+    config: &config::Reddit,
+    rng: &mut impl rand::Rng,
+) -> Result<Vec<reddit::Post>, String> {
+    if let Some(id) = &config.post {
+        println!("Fetching post {id}...");
+        let post = reddit::fetch_post(id)
+            .map_err(|err| format!("Failed to fetch post - {err}"))?
+            .ok_or_else(|| format!("Post '{id}' not found"))?;
+        return Ok(vec![post]);
+    }
 
-    // Get posts
-    let posts = reddit::fetch_posts(&config).expect("Failed to fetch posts");
+    let mut feeds = Vec::new();
+    for feed in reddit::feeds(config) {
+        println!(
+            "Fetching {} posts of r/{}...",
+            reddit::sort_and_time(&feed),
+            feed.subreddit
+        );
+        let posts = reddit::fetch_posts(&feed, &config.exclude)
+            .map_err(|err| format!("Failed to fetch posts - {err}"))?;
+        feeds.push((feed.weight, posts));
+    }
+
+    Ok(reddit::merge_posts(feeds, config.merge, rng))
+}
 
+/// Select texts from posts, or from comments of a post chosen by user
+pub fn select_texts(
+    config: &config::Reddit,
+    posts: Vec<reddit::Post>,
+) -> Result<Vec<Text>, String> {
     // Choose posts or comments
     let texts = if !config.comments {
         posts.to_texts()
     } else {
-        // Select post to get comments of, with user input
-        let parent_post = choose_parent_post(posts);
+        // Select post to get comments of, with user input, unless set in config
+        let parent_post = match (&config.post, config.choose) {
+            (None, reddit::ChoosePost::Prompt) => choose_parent_post(posts),
+            _ => posts
+                .into_iter()
+                .next()
+                .ok_or("No posts to take comments from")?,
+        };
 
         // Get comments of post
-        println!("Fetching top comments from chosen post...");
-        let comments =
-            reddit::fetch_comments(&config, &parent_post.link).expect("Failed to fetch comments");
+        println!(
+            "Fetching {} comments from chosen post...",
+            config.comment_sort
+        );
+        let comments = reddit::fetch_comments(config, &parent_post.link)
+            .map_err(|err| format!("Failed to fetch comments - {err}"))?;
 
         // Get texts, including parent post texts
-        let mut texts = vec![parent_post.title.clone()];
+        let mut texts =
+            vec![Text::new(TextKind::Title, parent_post.title.clone())
+                .with_source(parent_post.source())];
         texts.append(&mut comments.to_texts());
         texts
     };
 
     // Limit amount of text frames
-    texts
+    Ok(texts
         .into_iter()
-        .filter(|text| !text.is_empty())
-        .take(config.limit as usize)
-        .collect()
+        .filter(|text| !text.content.is_empty())
+        .take(config.limit)
+        .collect())
 }
 
 /// User select post to get comments of
@@ -71,47 +187,27 @@ fn choose_parent_post(posts: Vec<reddit::Post>) -> reddit::Post {
         .expect("Error reading input")
 }
 
-/// Create temp directory, empty contents, and return path
-pub fn create_empty_temp_dir() -> String {
-    let dir = get_temp_dir();
+/// Create directory for one part of video inside temp directory, and return path
+pub fn create_part_dir(dir: &str, index: usize) -> String {
+    let dir = format!("{dir}/part{index}");
 
-    // Remove and re-create
-    remove_temp_dir(&dir);
-    fs::create_dir(&dir).expect("Failed to create temp dir");
-
-    // Create subfolders
+    // Create with subfolders, or keep existing if resuming
     let folders = &["audio"];
     for folder in folders {
-        fs::create_dir(format!("{dir}/{folder}")).expect("Failed to create folder in temp dir");
+        fs::create_dir_all(format!("{dir}/{folder}")).expect("Failed to create folder in part dir");
     }
 
     dir
 }
 
-/// Remove temp directory if it exists
-pub fn remove_temp_dir(dir: &str) {
-    if Path::new(&dir).exists() {
-        fs::remove_dir_all(&dir).expect("Failed to remove temp dir");
-    }
-}
-
-// Get path to temp directory
-fn get_temp_dir() -> String {
-    let temp = env::temp_dir().to_string_lossy().to_string();
-    let name = env!("CARGO_PKG_NAME");
-    format!("{temp}/{name}")
-}
-
-/// Save voices to temp directory, create list file for ffmpeg
+/// Save voices to temp directory
+///
+/// Silent voices have no audio, and are skipped
 pub fn save_voices(voices: &[Voice], dir: &str) {
-    let mut inputs_file = Vec::new();
     for (i, voice) in voices.iter().enumerate() {
-        // Save audio to file
+        if voice.bytes.is_empty() {
+            continue;
+        }
         fs::write(format!("{dir}/audio/{i}.mp3"), &voice.bytes).expect("Failed to save voice file");
-        // Add audio to list
-        inputs_file.push(format!("file 'audio/{}.mp3'", i));
     }
-    // Save list file
-    fs::write(format!("{dir}/voices.txt"), inputs_file.join("\n"))
-        .expect("Failed to save inputs file");
 }
@@ -1,8 +1,10 @@
-use std::fs;
+use std::{env, fmt::Display, fs, process, str::FromStr};
 
 use reddit_video::{
-    config::Config, create_empty_temp_dir, fetch_posts_or_comments, remove_temp_dir, save_voices,
-    video, voice,
+    batch::{self, Batch},
+    config::Config,
+    daemon::{self, Daemon},
+    job, pipeline, video,
 };
 use stilo::println_styles;
 
@@ -17,39 +19,144 @@ fn main() {
 
     const CONFIG_FILENAME: &str = "./config.toml";
 
-    let config: Config = fs::read_to_string(CONFIG_FILENAME)
-        .unwrap_or(String::new())
-        .parse()
-        .expect("Failed to parse config file");
+    let args: Vec<String> = env::args().collect();
+    // Print plan of video, without fetching voices or rendering
+    let dry_run = args.iter().any(|arg| arg == "--dry-run");
+    // Render fast, low-resolution preview of first part
+    let preview = args.iter().any(|arg| arg == "--preview");
+    // Continue previous job from last completed stage, with optional job id
+    let resume = flag_value(&args, "--resume");
+    // Render each job of batch file, instead of a single video
+    let batch_path = flag_value(&args, "--batch")
+        .map(|path| path.expect("Missing path of batch file after `--batch`"));
+    // Run jobs of daemon file on their schedules, until stopped
+    let daemon_path = flag_value(&args, "--daemon")
+        .map(|path| path.expect("Missing path of daemon file after `--daemon`"));
+    // Amount of batch jobs to run at once, overriding batch file
+    let parallel = flag_value(&args, "--parallel").map(|count| {
+        count
+            .and_then(|count| count.parse::<usize>().ok())
+            .expect("Missing amount of jobs after `--parallel`")
+    });
+
+    // Default config is used if file does not exist
+    let config_text = fs::read_to_string(CONFIG_FILENAME).unwrap_or_default();
+    let config: Config = parse_or_exit(&config_text, "config file", CONFIG_FILENAME);
 
     println!("{:#?}", config);
+    if config.outro.length.is_some() {
+        eprintln!("[warning] `length` of `[outro]` is deprecated, use `outro` of `[out]`");
+    }
+    if config.out.overwrite.is_some() {
+        eprintln!("[warning] `overwrite` of `[out]` is deprecated, use `collision` of `[out]`");
+    }
+
+    // Nothing is rendered in dry run, so ffmpeg is not needed
+    if !dry_run {
+        info!("Checking ffmpeg...");
+        match video::preflight(&config) {
+            Ok(tools) => println!("{:#?}", tools),
+            Err(err) => {
+                eprintln!("[error] {err}");
+                process::exit(1);
+            }
+        }
+    }
+
+    match job::remove_stale_jobs(&config.out) {
+        Ok(0) => {}
+        Ok(removed) => println!("[info] Removed {removed} old job directories"),
+        Err(err) => {
+            eprintln!("[error] {err}");
+            process::exit(1);
+        }
+    }
 
-    let temp_dir = create_empty_temp_dir();
+    if let Some(batch_path) = batch_path {
+        let batch: Batch = parse_or_exit(&read_or_exit(batch_path), "batch file", batch_path);
+        let parallel = parallel.unwrap_or(batch.parallel);
 
-    info!("Fetching content...");
-    let texts = fetch_posts_or_comments(&config.reddit);
-    // let texts = vec![
-    //     "this is some text".to_string(),
-    //     // "some more text\nactually".to_string(),
-    //     // "blah blah".to_string(),
-    // ];
+        // Progress bars of parallel jobs would overwrite each other
+        let options = pipeline::Options {
+            dry_run,
+            progress: parallel <= 1,
+            ..Default::default()
+        };
 
-    println!("{:#?}", texts);
+        let count = batch.jobs.len();
+        info!("Running {count} jobs, {parallel} at once...");
+        let reports = batch::run_batch(&config_text, &batch, &options, parallel);
 
-    info!("Creating voices...");
-    let voices = voice::create_voices(&config.voice, texts).expect("Failed to fetch voices");
-    info!("Saving voices...");
-    save_voices(&voices, &temp_dir);
+        info!("Batch report:");
+        batch::print_report(&reports);
+        match batch::save_report(batch_path, &reports) {
+            Ok(path) => println!("Saved report to {path}"),
+            Err(err) => eprintln!("[warning] {err}"),
+        }
+        if !reports.iter().all(batch::JobReport::is_success) {
+            process::exit(1);
+        }
+        return;
+    }
 
-    info!("Concatenating audio...");
-    video::concat_voices(&config, &temp_dir);
+    if let Some(daemon_path) = daemon_path {
+        let daemon: Daemon = parse_or_exit(&read_or_exit(daemon_path), "daemon file", daemon_path);
 
-    info!("Adding audio to video...");
-    video::apply_video_audio(&config, &temp_dir);
+        let options = pipeline::Options {
+            dry_run,
+            ..Default::default()
+        };
 
-    info!("Rendering video with text...");
-    video::render_video(&config, &temp_dir, &voices);
+        let count = daemon.jobs.len();
+        info!("Running {count} scheduled jobs...");
+        if let Err(err) = daemon::run_daemon(&config_text, &daemon, &options) {
+            eprintln!("[error] {err}");
+            process::exit(1);
+        }
+        return;
+    }
+
+    let options = pipeline::Options {
+        dry_run,
+        preview,
+        resume: resume.is_some(),
+        job_id: resume.flatten().map(str::to_string),
+        progress: true,
+    };
+    if let Err(err) = pipeline::run(&config, &options) {
+        eprintln!("[error] {err}");
+        process::exit(1);
+    }
 
     println_styles!("Completed successfully!": Green + bold);
-    remove_temp_dir(&temp_dir);
+}
+
+/// Read file, or print error and exit
+fn read_or_exit(path: &str) -> String {
+    fs::read_to_string(path).unwrap_or_else(|err| {
+        eprintln!("[error] Failed to read '{path}' - {err}");
+        process::exit(1);
+    })
+}
+
+/// Parse toml file, or print error with location of invalid value and exit
+fn parse_or_exit<T>(text: &str, kind: &str, path: &str) -> T
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    text.parse().unwrap_or_else(|err| {
+        eprintln!("[error] Invalid {kind} '{path}'\n{err}");
+        process::exit(1);
+    })
+}
+
+/// Find flag in arguments, with the value following it, if it is not another flag
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<Option<&'a str>> {
+    let i = args.iter().position(|arg| arg == flag)?;
+    Some(
+        args.get(i + 1)
+            .filter(|arg| !arg.starts_with("--"))
+            .map(String::as_str),
+    )
 }
@@ -0,0 +1,325 @@
+use stilo::println_styles;
+
+use crate::{
+    config::Config,
+    create_part_dir, fetch_posts,
+    job::{Job, Stage},
+    save_voices, select_texts, video, voice, Source, TextKind,
+};
+
+macro_rules! info {
+    ( $($tt:tt)* ) => {
+        println_styles!( $($tt)*: Cyan );
+    };
+}
+
+/// Mixed into seed of job, to seed generator which merges posts of subreddits
+const MERGE_SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// How to run pipeline
+#[derive(Debug, Default)]
+pub struct Options {
+    /// Print plan of video, without fetching voices or rendering
+    pub dry_run: bool,
+    /// Render fast, low-resolution preview of first part
+    pub preview: bool,
+    /// Continue previous job from last completed stage
+    pub resume: bool,
+    /// Id of job to resume, or latest job if not set
+    pub job_id: Option<String>,
+    /// Print progress bar while rendering
+    pub progress: bool,
+}
+
+/// Result of successful run
+#[derive(Debug)]
+pub struct Summary {
+    /// Id of job
+    pub job_id: String,
+    /// Files which were saved
+    pub files: Vec<String>,
+    /// Ids of posts in video
+    pub posts: Vec<String>,
+}
+
+/// Run full pipeline, from fetching posts to rendering all parts of video
+///
+/// Job directory is closed according to cleanup policy, if run fails with an error. Panics
+/// release the lock of job, but keep its directory.
+pub fn run(config: &Config, options: &Options) -> Result<Summary, String> {
+    let Options {
+        dry_run, preview, ..
+    } = *options;
+
+    let job = if options.resume {
+        Job::resume(&config.out, options.job_id.as_deref())
+    } else {
+        Job::create(&config.out)
+    };
+    let mut job = job?;
+    if options.resume {
+        let (id, stage) = (&job.id, job.stage());
+        info!("Resuming job {id} after stage {stage:?}...");
+    } else {
+        println!("Job directory: {}", job.dir);
+    }
+
+    // Seed of resumed job is reused, so same posts, backgrounds, and music are chosen
+    let (mut rng, seed) = video::seeded_rng(job.seed().or(config.assets.seed));
+    job.set_seed(seed);
+
+    let voices = if job.completed(Stage::Synthesize) {
+        info!("Loading saved voices...");
+        job.load_voices()
+    } else {
+        let texts = if job.completed(Stage::Select) {
+            job.load_texts()
+        } else {
+            let posts = if job.completed(Stage::Fetch) {
+                job.load_posts()
+            } else {
+                info!("Fetching content...");
+                // Separate generator keeps choices of backgrounds the same, if fetch is skipped
+                // Its seed is derived, so it does not repeat the same sequence as backgrounds
+                let (mut merge_rng, _) = video::seeded_rng(Some(seed ^ MERGE_SEED));
+                match fetch_posts(&config.reddit, &mut merge_rng) {
+                    Ok(posts) => {
+                        job.save_posts(&posts);
+                        posts
+                    }
+                    Err(err) => {
+                        job.close(&config.out, false);
+                        return Err(err);
+                    }
+                }
+            };
+            let texts = match select_texts(&config.reddit, posts) {
+                Ok(texts) => texts,
+                Err(err) => {
+                    job.close(&config.out, false);
+                    return Err(err);
+                }
+            };
+            job.save_texts(&texts);
+            texts
+        };
+
+        println!("{:#?}", texts);
+
+        let (intro, mut voices) = if dry_run {
+            info!("Estimating voices...");
+            (
+                voice::estimate_intro(&config.voice, &config.intro, &texts),
+                voice::estimate_voices(&config.voice, texts),
+            )
+        } else {
+            info!("Creating voices...");
+            (
+                voice::create_intro(&config.voice, &config.intro, &texts)
+                    .expect("Failed to fetch intro voice"),
+                voice::create_voices(&config.voice, texts).expect("Failed to fetch voices"),
+            )
+        };
+        if let Some(intro) = intro {
+            voices.insert(0, intro);
+        }
+        // Estimated voices have no audio, so are not saved
+        if !dry_run {
+            job.save_voices(&voices);
+        }
+        voices
+    };
+    let parts = video::split_parts(config, voices);
+
+    // Same time is used for filenames of all parts
+    let now = chrono::Local::now();
+    // First post of video names and describes every part, including parts with only comments
+    let post = parts
+        .iter()
+        .flatten()
+        .map(|voice| &voice.text)
+        .find(|text| text.kind == TextKind::Title);
+    let mut manifest = video::ManifestEntry::new(post, &now);
+
+    // Progress bar is not printed if disabled, such as when jobs run in parallel
+    let on_progress = |progress: &video::Progress| {
+        if options.progress {
+            video::print_progress(progress);
+        }
+    };
+
+    for (i, voices) in parts.iter().enumerate() {
+        // Only number parts if video was split
+        let part = (parts.len() > 1).then_some(i + 1);
+        if let Some(part) = part {
+            let count = parts.len();
+            info!("Part {part} of {count}...");
+        }
+
+        // Background and music are chosen for every part, so random choices stay in sequence
+        // Dry run does not call ffprobe
+        info!("Choosing background...");
+        let duration = video::total_duration(config, voices) + video::outro_duration(config);
+        let background = match video::choose_background(config, &mut rng, seed, duration, !dry_run)
+        {
+            Ok(background) => background,
+            Err(err) => {
+                job.close(&config.out, false);
+                return Err(format!("Failed to choose background - {err}"));
+            }
+        };
+        let music = video::choose_music(config, &mut rng, !dry_run);
+
+        if !dry_run && !preview && job.is_rendered(i) {
+            println!("[info] Part was already rendered, skipping");
+            continue;
+        }
+
+        // Preview is saved to its own file, which is always replaced
+        let output = if preview {
+            video::Output::single(&config.preview.name)
+        } else {
+            // Dry run does not create output file
+            match video::resolve_output(config, post, part, &now, !dry_run) {
+                Ok(Some(output)) => output,
+                Ok(None) => {
+                    println!("[info] Output file already exists, skipping");
+                    continue;
+                }
+                Err(err) => {
+                    job.close(&config.out, false);
+                    return Err(err);
+                }
+            }
+        };
+        let part_dir = create_part_dir(&job.dir, i);
+
+        if !dry_run {
+            info!("Saving voices...");
+            save_voices(voices, &part_dir);
+        }
+
+        if dry_run {
+            info!("Planning video...");
+            let render = video::build_render(
+                config,
+                &part_dir,
+                voices,
+                &background,
+                music.as_deref(),
+                &output,
+                None,
+            );
+            let plan = video::plan(
+                config,
+                voices,
+                &background,
+                music.as_deref(),
+                &render,
+                &output,
+            );
+            plan.print();
+            plan.save(&output);
+            continue;
+        }
+
+        println!("{:#?}", background);
+        if let Some(music) = &music {
+            println!("Music: {music}");
+        }
+
+        if preview {
+            info!("Rendering preview...");
+            let result = video::render_preview(
+                config,
+                &part_dir,
+                voices,
+                &background,
+                music.as_deref(),
+                &output,
+                on_progress,
+            )
+            .map_err(|err| err.to_string())
+            .and_then(|()| output.finish())
+            .and_then(|()| video::save_thumbnails(config, voices));
+            if let Err(err) = result {
+                job.close(&config.out, false);
+                return Err(err);
+            }
+            manifest.add_file(&output.path);
+            break;
+        }
+
+        manifest.add_file(&video::save_background(&output, &background));
+
+        info!("Rendering video...");
+        let result = video::render_video(
+            config,
+            &part_dir,
+            voices,
+            &background,
+            music.as_deref(),
+            &output,
+            on_progress,
+        )
+        .map_err(|err| err.to_string())
+        .and_then(|()| output.finish());
+        if let Err(err) = result {
+            // Job directory is kept by default, to inspect failed frame and resume job
+            // Reserved output file is removed when output is dropped
+            job.close(&config.out, false);
+            return Err(err);
+        }
+        manifest.add_file(&output.path);
+
+        if config.metadata.enabled {
+            match video::save_metadata(config, post, voices, &output) {
+                Ok(path) => {
+                    println!("Saved metadata to {path}");
+                    manifest.add_file(&path);
+                }
+                Err(err) => eprintln!("[warning] {err}"),
+            }
+        }
+
+        if config.thumbnail.enabled {
+            info!("Creating thumbnail...");
+            match video::create_thumbnail(config, &part_dir, voices, &background, &output) {
+                Ok(Some(path)) => {
+                    println!("Saved thumbnail to {path}");
+                    manifest.add_file(&path);
+                }
+                Ok(None) => println!("[info] Thumbnail already exists, skipping"),
+                Err(err) => eprintln!("[warning] {err}"),
+            }
+        }
+
+        job.complete_part(i, parts.len());
+    }
+
+    let files = manifest.files.clone();
+    // Preview is not recorded in manifest
+    if config.out.manifest && !preview && !files.is_empty() {
+        match video::save_manifest(config, manifest) {
+            Ok(path) => println!("Saved manifest to {path}"),
+            Err(err) => eprintln!("[warning] {err}"),
+        }
+    }
+
+    // Titles are included for every post in video, including parent post of comments
+    let mut posts: Vec<String> = Vec::new();
+    for voice in parts.iter().flatten() {
+        let id = voice.text.source.as_ref().and_then(Source::post_id);
+        if let Some(id) = id.filter(|id| !posts.iter().any(|post| post == id)) {
+            posts.push(id.to_string());
+        }
+    }
+
+    let job_id = job.id.clone();
+    job.close(&config.out, true);
+    Ok(Summary {
+        job_id,
+        files,
+        posts,
+    })
+}
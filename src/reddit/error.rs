@@ -0,0 +1,63 @@
+use reqwest::StatusCode;
+use std::fmt::Display;
+
+/// Error fetching from Reddit API
+#[derive(Debug)]
+pub enum RedditError {
+    /// Request failed, such as with no connection
+    Request(reqwest::Error),
+    /// Response has error status, such as for a private or missing subreddit
+    Status { url: String, status: StatusCode },
+    /// Response is not the expected JSON, such as an error page
+    Parse {
+        url: String,
+        reason: String,
+        /// Start of response
+        snippet: String,
+    },
+}
+
+impl RedditError {
+    /// Create parse error, with start of response text
+    pub(super) fn parse(url: &str, error: serde_json::Error, text: &str) -> Self {
+        Self::Parse {
+            url: url.to_string(),
+            reason: error.to_string(),
+            snippet: text.chars().take(80).collect(),
+        }
+    }
+}
+
+impl Display for RedditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Request(error) => write!(f, "Request to Reddit failed - {error}"),
+            Self::Status { url, status } => {
+                let reason = match *status {
+                    StatusCode::NOT_FOUND => "subreddit or post does not exist",
+                    StatusCode::FORBIDDEN => "subreddit is private or quarantined",
+                    StatusCode::TOO_MANY_REQUESTS => "too many requests, try again later",
+                    _ => status.canonical_reason().unwrap_or("unknown error"),
+                };
+                write!(f, "Reddit responded with {status} for '{url}': {reason}")
+            }
+            Self::Parse {
+                url,
+                reason,
+                snippet,
+            } => write!(
+                f,
+                "Unexpected response from '{url}', check subreddit name and sort - {reason}\n  \
+                Response starts with: {snippet}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RedditError {}
+
+impl From<reqwest::Error> for RedditError {
+    fn from(error: reqwest::Error) -> Self {
+        Self::Request(error)
+    }
+}
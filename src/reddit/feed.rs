@@ -0,0 +1,152 @@
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+use serde::Deserialize;
+use std::collections::{HashSet, VecDeque};
+
+use super::{Post, Sort, Time};
+use crate::config;
+
+/// How to combine posts of multiple subreddits
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Merge {
+    /// Take one post from each subreddit in turn
+    #[default]
+    Interleave,
+    /// Sort all posts by score
+    Score,
+    /// Take posts from randomly chosen subreddits, weighted by `weight` of each
+    Random,
+}
+
+/// Subreddit to fetch posts from, with settings of `[reddit]` applied
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feed {
+    /// Subreddit or multireddit name, without `r/`
+    pub subreddit: String,
+    pub sort: Sort,
+    pub time: Time,
+    /// Amount of posts to fetch
+    pub limit: usize,
+    /// Share of posts when merging randomly
+    pub weight: f32,
+}
+
+/// Subreddits of config, each with its settings
+pub fn feeds(config: &config::Reddit) -> Vec<Feed> {
+    config
+        .subreddit
+        .sources()
+        .into_iter()
+        .map(|source| Feed {
+            subreddit: source.name,
+            sort: source.sort.unwrap_or(config.sort),
+            time: source.time.unwrap_or(config.time),
+            limit: source.limit.unwrap_or(config.limit),
+            weight: source.weight,
+        })
+        .collect()
+}
+
+/// Combine posts of each subreddit, with weight of subreddit, removing duplicate posts
+pub fn merge_posts(feeds: Vec<(f32, Vec<Post>)>, merge: Merge, rng: &mut impl Rng) -> Vec<Post> {
+    let mut posts = match merge {
+        Merge::Interleave => interleave(feeds.into_iter().map(|(_, posts)| posts).collect()),
+        Merge::Score => {
+            let mut posts: Vec<Post> = feeds.into_iter().flat_map(|(_, posts)| posts).collect();
+            // Stable sort keeps order of posts with same score
+            posts.sort_by_key(|post| std::cmp::Reverse(post.score));
+            posts
+        }
+        Merge::Random => merge_random(feeds, rng),
+    };
+
+    // Multireddits and lists can overlap
+    let mut links = HashSet::new();
+    posts.retain(|post| links.insert(post.link.clone()));
+    posts
+}
+
+/// Take next post of each subreddit in turn, until all are empty
+fn interleave(feeds: Vec<Vec<Post>>) -> Vec<Post> {
+    let mut queues: Vec<VecDeque<Post>> = feeds.into_iter().map(VecDeque::from).collect();
+    let mut posts = Vec::new();
+    while queues.iter().any(|queue| !queue.is_empty()) {
+        posts.extend(queues.iter_mut().filter_map(VecDeque::pop_front));
+    }
+    posts
+}
+
+/// Take next post of a random subreddit, chosen by weight, until all are empty
+fn merge_random(feeds: Vec<(f32, Vec<Post>)>, rng: &mut impl Rng) -> Vec<Post> {
+    let (weights, feeds): (Vec<f32>, Vec<Vec<Post>>) = feeds.into_iter().unzip();
+    let mut queues: Vec<VecDeque<Post>> = feeds.into_iter().map(VecDeque::from).collect();
+
+    let mut posts = Vec::new();
+    loop {
+        // Empty subreddits can no longer be chosen
+        let remaining: Vec<f32> = queues
+            .iter()
+            .zip(&weights)
+            .map(|(queue, weight)| {
+                if queue.is_empty() {
+                    0.0
+                } else {
+                    weight.max(0.0)
+                }
+            })
+            .collect();
+        // Fails if no subreddits with weight are left
+        let Ok(distribution) = WeightedIndex::new(&remaining) else {
+            break;
+        };
+        let index = distribution.sample(rng);
+        posts.extend(queues[index].pop_front());
+    }
+
+    // Posts of subreddits with no weight go last
+    posts.extend(interleave(queues.into_iter().map(Vec::from).collect()));
+    posts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(link: &str, score: u32) -> Post {
+        Post {
+            title: link.to_string(),
+            body: String::new(),
+            link: link.to_string(),
+            score,
+            comment_count: 0,
+            author: String::new(),
+            subreddit: String::new(),
+            flair: None,
+        }
+    }
+
+    fn links(posts: &[Post]) -> Vec<&str> {
+        posts.iter().map(|post| post.link.as_str()).collect()
+    }
+
+    #[test]
+    fn posts_are_merged() {
+        let feeds = || {
+            vec![
+                (1.0, vec![post("a1", 5), post("a2", 1), post("a3", 0)]),
+                (0.0, vec![post("b1", 9), post("a2", 1)]),
+            ]
+        };
+        let mut rng = rand::thread_rng();
+
+        let posts = merge_posts(feeds(), Merge::Interleave, &mut rng);
+        assert_eq!(links(&posts), ["a1", "b1", "a2", "a3"]);
+
+        let posts = merge_posts(feeds(), Merge::Score, &mut rng);
+        assert_eq!(links(&posts), ["b1", "a1", "a2", "a3"]);
+
+        // Subreddit without weight is only taken from after others are empty
+        let posts = merge_posts(feeds(), Merge::Random, &mut rng);
+        assert_eq!(links(&posts), ["a1", "a2", "a3", "b1"]);
+    }
+}
@@ -16,6 +16,7 @@ macro_rules! json {
 
     (@single $struct:ident ( $( $type:ty ),* $(,)? ) ) => {
         /// Deserialized JSON
+        #[allow(dead_code)]
         #[derive(Debug, serde::Deserialize)]
         pub struct $struct (
             $( pub $type, )*
@@ -41,6 +42,9 @@ pub mod subreddit {
             permalink: String,
             score: i32,
             num_comments: u32,
+            author: String,
+            subreddit: String,
+            link_flair_text: Option<String>,
         }
     }
 }
@@ -63,6 +67,10 @@ pub mod post {
         }
         ChildData {
             body: Option<String>,
+            author: Option<String>,
+            score: Option<i32>,
+            subreddit: Option<String>,
+            permalink: Option<String>,
         }
     }
 }
@@ -0,0 +1,123 @@
+use serde::Deserialize;
+use std::fmt::Display;
+
+/// Sort of posts of subreddit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sort {
+    Hot,
+    New,
+    #[default]
+    Top,
+    Rising,
+    Controversial,
+    Best,
+}
+
+impl Sort {
+    /// Whether posts are limited to a time period
+    pub fn has_time(self) -> bool {
+        matches!(self, Self::Top | Self::Controversial)
+    }
+
+    /// Name in config
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Best => "best",
+            _ => self.as_str(),
+        }
+    }
+
+    /// Name in URL of listing
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Hot => "hot",
+            Self::New => "new",
+            Self::Top => "top",
+            Self::Rising => "rising",
+            Self::Controversial => "controversial",
+            // Best is only listed for front page, and is hot for a subreddit
+            Self::Best => "hot",
+        }
+    }
+}
+
+/// Time period of posts, for `top` and `controversial` sorts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Time {
+    Hour,
+    Day,
+    Week,
+    #[default]
+    Month,
+    Year,
+    All,
+}
+
+impl Time {
+    /// Name in URL of listing
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Hour => "hour",
+            Self::Day => "day",
+            Self::Week => "week",
+            Self::Month => "month",
+            Self::Year => "year",
+            Self::All => "all",
+        }
+    }
+}
+
+/// Sort of comments of post
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentSort {
+    #[default]
+    Top,
+    Best,
+    Controversial,
+    New,
+    /// Question and answer, with replies of post author
+    Qa,
+}
+
+impl CommentSort {
+    /// Name in config
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Best => "best",
+            _ => self.as_str(),
+        }
+    }
+
+    /// Name in URL of comments
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Top => "top",
+            // Best is called confidence by the API
+            Self::Best => "confidence",
+            Self::Controversial => "controversial",
+            Self::New => "new",
+            Self::Qa => "qa",
+        }
+    }
+}
+
+impl Display for Sort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl Display for Time {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Display for CommentSort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
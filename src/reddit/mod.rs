@@ -1,19 +1,41 @@
+/// Error of API requests
+mod error;
+/// Combine posts of multiple subreddits
+mod feed;
 /// Deserialize JSON returned from API response
 mod json;
+/// Sorts and time periods of listings
+mod listing;
 /// Format numbers nicely
 mod number;
 
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::fmt::Display;
 
 use self::json::{post, subreddit};
-use self::number::format_number;
-use crate::{config, ToTexts};
+use crate::{config, Source, Text, TextKind, ToTexts};
+
+pub use self::error::RedditError;
+pub use self::feed::{feeds, merge_posts, Feed, Merge};
+pub use self::listing::{CommentSort, Sort, Time};
+pub use self::number::format_number;
 
 /// User agent for Reddit API requests
 const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; WOW64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/113.0.5666.197 Safari/537.36";
 
+/// How to choose post to take comments from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChoosePost {
+    /// Ask user to choose from list
+    #[default]
+    Prompt,
+    /// Take first post, in sort order, such as when running unattended
+    First,
+}
+
 /// Reddit post
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Post {
     /// Title
     pub title: String,
@@ -25,11 +47,34 @@ pub struct Post {
     pub score: u32,
     /// Amount of comments on post (`num_comments`)
     pub comment_count: u32,
+    /// Username of author
+    pub author: String,
+    /// Subreddit name, without `r/`
+    pub subreddit: String,
+    /// Flair text, if set (`link_flair_text`)
+    pub flair: Option<String>,
+}
+
+impl Post {
+    /// Subreddit, author, score, link, and flair of post
+    pub fn source(&self) -> Source {
+        Source {
+            subreddit: self.subreddit.clone(),
+            author: self.author.clone(),
+            score: self.score,
+            link: Some(self.link.clone()),
+            flair: self.flair.clone(),
+        }
+    }
 }
 
 impl ToTexts for Post {
-    fn to_texts(self) -> Vec<String> {
-        vec![self.title, self.body]
+    fn to_texts(self) -> Vec<Text> {
+        let source = self.source();
+        vec![
+            Text::new(TextKind::Title, self.title).with_source(source.clone()),
+            Text::new(TextKind::Body, self.body).with_source(source),
+        ]
     }
 }
 
@@ -50,11 +95,26 @@ impl Display for Post {
 pub struct Comment {
     /// Body
     pub body: String,
+    /// Username of author
+    pub author: String,
+    /// Upvote score
+    pub score: u32,
+    /// Subreddit name, without `r/`
+    pub subreddit: String,
+    /// Link to comment, in subreddit (`permalink`)
+    pub link: Option<String>,
 }
 
 impl ToTexts for Comment {
-    fn to_texts(self) -> Vec<String> {
-        vec![self.body]
+    fn to_texts(self) -> Vec<Text> {
+        let source = Source {
+            subreddit: self.subreddit,
+            author: self.author,
+            score: self.score,
+            link: self.link,
+            flair: None,
+        };
+        vec![Text::new(TextKind::Comment, self.body).with_source(source)]
     }
 }
 
@@ -67,25 +127,56 @@ fn build_client() -> reqwest::blocking::Client {
         .expect("Error building simple reqwest client")
 }
 
-/// Fetch posts of subreddit
-pub fn fetch_posts(config: &config::Reddit) -> Result<Vec<Post>, reqwest::Error> {
-    let config::Reddit {
+/// Fetch JSON response, with error for error status or unexpected response
+fn fetch_json<T: DeserializeOwned>(url: &str) -> Result<T, RedditError> {
+    let response = build_client().get(url).send()?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(RedditError::Status {
+            url: url.to_string(),
+            status,
+        });
+    }
+
+    let text = response.text()?;
+    serde_json::from_str(&text).map_err(|error| RedditError::parse(url, error, &text))
+}
+
+/// Fetch posts of subreddit, skipping posts with excluded ids
+pub fn fetch_posts(feed: &Feed, exclude: &[String]) -> Result<Vec<Post>, RedditError> {
+    let Feed {
         subreddit,
         sort,
         time,
         limit,
         ..
-    } = config;
+    } = feed;
 
-    // Get text from API
+    // Get response from API
+    let sort = sort.as_str();
     let url = format!("https://reddit.com/r/{subreddit}/{sort}.json?t={time}&count=10000");
-    let text = build_client().get(&url).send()?.text()?;
+    let subreddit: subreddit::Response = fetch_json(&url)?;
 
-    // Serialize response
-    let subreddit: subreddit::Response =
-        serde_json::from_str(&text).expect("Failed to parse json!");
+    let mut posts = parse_posts(subreddit);
+    posts.retain(|post| {
+        let source = post.source();
+        !exclude.iter().any(|id| source.post_id() == Some(id))
+    });
+    posts.truncate(*limit);
+    Ok(posts)
+}
+
+/// Fetch single post by id
+pub fn fetch_post(id: &str) -> Result<Option<Post>, RedditError> {
+    // Get response from API
+    let url = format!("https://reddit.com/comments/{id}.json?limit=1");
+    let post: post::Response = fetch_json(&url)?;
 
-    // Get posts
+    Ok(parse_posts(post.0).into_iter().next())
+}
+
+/// Get posts from listing of subreddit
+fn parse_posts(subreddit: subreddit::Response) -> Vec<Post> {
     let mut posts = Vec::new();
     for child in subreddit.data.children {
         let subreddit::ChildData {
@@ -94,6 +185,9 @@ pub fn fetch_posts(config: &config::Reddit) -> Result<Vec<Post>, reqwest::Error>
             permalink,
             score,
             num_comments,
+            author,
+            subreddit,
+            link_flair_text,
         } = child.data;
 
         posts.push(Post {
@@ -102,41 +196,53 @@ pub fn fetch_posts(config: &config::Reddit) -> Result<Vec<Post>, reqwest::Error>
             link: permalink,
             score: score.max(0) as u32,
             comment_count: num_comments,
+            author,
+            subreddit,
+            flair: link_flair_text.filter(|flair| !flair.is_empty()),
         });
-
-        if &posts.len() >= limit {
-            break;
-        }
     }
-
-    Ok(posts)
+    posts
 }
 
-/// Fetch top comments of post
+/// Fetch comments of post, in sort of config
 pub fn fetch_comments(
     config: &config::Reddit,
     parent_link: &str,
-) -> Result<Vec<Comment>, reqwest::Error> {
-    let config::Reddit { limit, .. } = config;
-
-    // Get text from API
-    let url = format!("https://reddit.com/{parent_link}.json?limit=10000");
-    let text = build_client().get(&url).send()?.text()?;
+) -> Result<Vec<Comment>, RedditError> {
+    let config::Reddit {
+        limit,
+        comment_sort,
+        ..
+    } = config;
 
-    // Serialize response
-    let post: post::Response = serde_json::from_str(&text).expect("Failed to parse json!");
+    // Get response from API
+    let sort = comment_sort.as_str();
+    let url = format!("https://reddit.com/{parent_link}.json?limit=10000&sort={sort}");
+    let post: post::Response = fetch_json(&url)?;
 
     // Get comments
     let mut comments = Vec::new();
     for child in post.1.data.children {
-        let body = child.data.body;
+        let post::ChildData {
+            body,
+            author,
+            score,
+            subreddit,
+            permalink,
+        } = child.data;
 
         let Some(body) = body else {
             println!("  [info] comment missing body");
             continue;
         };
 
-        comments.push(Comment { body });
+        comments.push(Comment {
+            body,
+            author: author.unwrap_or_else(|| "[deleted]".to_string()),
+            score: score.unwrap_or_default().max(0) as u32,
+            subreddit: subreddit.unwrap_or_default(),
+            link: permalink,
+        });
 
         if &comments.len() >= limit {
             break;
@@ -146,11 +252,11 @@ pub fn fetch_comments(
     Ok(comments)
 }
 
-/// Display 'sort' and 'time' of Reddit config
-pub fn sort_and_time(config: &config::Reddit) -> String {
-    let config::Reddit { time, sort, .. } = config;
-    if sort == "top" {
-        if time == "all" {
+/// Display 'sort' and 'time' of subreddit
+pub fn sort_and_time(feed: &Feed) -> String {
+    let Feed { time, sort, .. } = feed;
+    if sort.has_time() {
+        if *time == Time::All {
             format!("{sort} of all time")
         } else {
             format!("{sort} of the {time}")
@@ -159,3 +265,51 @@ pub fn sort_and_time(config: &config::Reddit) -> String {
         sort.to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sort_and_time_works() {
+        let feed = |sort, time| Feed {
+            subreddit: "jokes".to_string(),
+            sort,
+            time,
+            limit: 10,
+            weight: 1.0,
+        };
+        assert_eq!(
+            sort_and_time(&feed(Sort::Top, Time::Week)),
+            "top of the week"
+        );
+        assert_eq!(
+            sort_and_time(&feed(Sort::Controversial, Time::All)),
+            "controversial of all time"
+        );
+        assert_eq!(sort_and_time(&feed(Sort::Hot, Time::Week)), "hot");
+        // Name of config is shown, not name in URL
+        assert_eq!(sort_and_time(&feed(Sort::Best, Time::Week)), "best");
+        assert_eq!(Sort::Best.as_str(), "hot");
+        assert_eq!(CommentSort::Best.to_string(), "best");
+    }
+
+    #[test]
+    fn subreddit_lists_report_errors_of_settings() {
+        let parse = |subreddit: &str| {
+            format!("[reddit]\nsubreddit = {subreddit}").parse::<crate::config::Config>()
+        };
+        let config = parse(r#"["jokes", { name = "news", sort = "new" }]"#).unwrap();
+        let sources = config.reddit.subreddit.sources();
+        assert_eq!(sources[0].name, "jokes");
+        assert_eq!(sources[1].sort, Some(Sort::New));
+
+        let err = parse(r#"[{ name = "news", sort = "newest" }]"#).unwrap_err();
+        assert!(
+            err.to_string().contains("unknown variant `newest`"),
+            "{err}"
+        );
+        let err = parse(r#"[{ name = "news", weigth = 2.0 }]"#).unwrap_err();
+        assert!(err.to_string().contains("unknown field `weigth`"), "{err}");
+    }
+}
@@ -32,7 +32,7 @@ fn human_number(number: u32) -> String {
         }
     }
 
-    return format!("{}B", divide_rounded(number, 1_000_000_000, 1));
+    format!("{}B", divide_rounded(number, 1_000_000_000, 1))
 }
 
 /// Divide an integer by another integer, rounding to a certain number of decimals
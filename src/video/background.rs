@@ -0,0 +1,161 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Serialize;
+use std::{fs, path::Path, time::Duration};
+
+use super::probe::{probe, VideoStream};
+use crate::config::{Config, MediaSource};
+
+/// File extensions of background videos, when reading from directory
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mkv", "webm", "mov"];
+/// File extensions of still images, which can be set as background
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "bmp"];
+
+/// Background clip chosen for one video
+#[derive(Debug, Serialize)]
+pub struct Background {
+    /// Path to video file
+    pub path: String,
+    /// Time to start video from, in seconds
+    pub offset: f32,
+    /// Whether video is looped, to cover length of audio
+    pub looped: bool,
+    /// Whether background is a still image, which is repeated as frames of video
+    pub still: bool,
+    /// Seed of random number generator used to choose clip and offset
+    pub seed: u64,
+    /// Video stream of file, read by ffprobe
+    #[serde(skip)]
+    pub video: VideoStream,
+}
+
+/// Random number generator, seeded with given seed (such as from config) or randomly
+///
+/// Returns generator and seed, so the choice can be recorded and reproduced
+pub fn seeded_rng(seed: Option<u64>) -> (StdRng, u64) {
+    let seed = seed.unwrap_or_else(rand::random);
+    (StdRng::seed_from_u64(seed), seed)
+}
+
+/// Choose a random background clip and start offset, which covers `duration`
+///
+/// Fails if chosen file cannot be probed, or has no video stream. Without probing, such as for
+/// a dry run, the clip is assumed to have the resolution of the profile, and start from the
+/// beginning.
+pub fn choose_background(
+    config: &Config,
+    rng: &mut StdRng,
+    seed: u64,
+    duration: Duration,
+    probe_clip: bool,
+) -> Result<Background, String> {
+    let paths = list_files(&config.assets.background, VIDEO_EXTENSIONS);
+    if paths.is_empty() {
+        return Err("No background videos found".to_string());
+    }
+    let path = paths[rng.gen_range(0..paths.len())].clone();
+
+    if !probe_clip {
+        let (width, height) = config.out.profile.resolution().unwrap_or((1920, 1080));
+        let still = Path::new(&path)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+        return Ok(Background {
+            path,
+            offset: 0.0,
+            looped: still,
+            still,
+            seed,
+            video: VideoStream {
+                index: 0,
+                width,
+                height,
+                frame_rate: None,
+            },
+        });
+    }
+
+    let info = probe(&config.ffmpeg.probe_path, &path)
+        .map_err(|err| format!("Failed to probe background '{path}' - {err}"))?;
+    let Some(video) = info.video else {
+        return Err(format!("Background '{path}' has no video stream"));
+    };
+
+    // Still images have no duration, and are looped to any length
+    let still = info.duration.is_none();
+    let clip_duration = info.duration.unwrap_or(Duration::ZERO);
+
+    // Loop if audio is longer than clip, otherwise keep offset within range which covers audio
+    let looped = clip_duration < duration;
+    if looped && info.duration.is_some() {
+        eprintln!(
+            "[warning] Background '{path}' ({:.1}s) is shorter than narration ({:.1}s), and will be looped",
+            clip_duration.as_secs_f32(),
+            duration.as_secs_f32()
+        );
+    }
+    let max_offset = if !config.assets.random_offset {
+        Duration::ZERO
+    } else if looped {
+        clip_duration
+    } else {
+        clip_duration - duration
+    };
+    let offset = if max_offset.is_zero() {
+        0.0
+    } else {
+        rng.gen_range(0..max_offset.as_millis() as u64) as f32 / 1000.0
+    };
+
+    Ok(Background {
+        path,
+        offset,
+        looped,
+        still,
+        seed,
+        video,
+    })
+}
+
+impl Background {
+    /// Input options of ffmpeg, to repeat background if it is looped
+    ///
+    /// Still image is repeated as frames with `-loop 1`, as `-stream_loop` would only repeat its
+    /// single frame
+    pub fn loop_args(&self) -> &'static [&'static str] {
+        if self.still {
+            &["-loop", "1"]
+        } else if self.looped {
+            &["-stream_loop", "-1"]
+        } else {
+            &[]
+        }
+    }
+}
+
+/// List files of media source, reading directories for files with given extensions
+pub fn list_files(source: &MediaSource, extensions: &[&str]) -> Vec<String> {
+    let mut files = Vec::new();
+    for path in source.paths() {
+        if !Path::new(path).is_dir() {
+            files.push(path.to_string());
+            continue;
+        }
+
+        let entries = fs::read_dir(path).expect("Failed to read media directory");
+        let mut entries: Vec<_> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| extensions.contains(&ext.to_lowercase().as_str()))
+            })
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        // Sort, so the same seed always chooses the same file
+        entries.sort();
+        files.append(&mut entries);
+    }
+    files
+}
@@ -1,5 +1,7 @@
 use std::time::Duration;
 
+use super::format_seconds;
+
 #[derive(Debug)]
 pub struct DrawtextOptions {
     pub font: String,
@@ -10,6 +12,7 @@ pub struct DrawtextOptions {
     pub boxcolor: String,
     pub x: String,
     pub y: String,
+    pub wrap_width: usize,
 }
 
 impl Default for DrawtextOptions {
@@ -23,6 +26,7 @@ impl Default for DrawtextOptions {
             boxcolor: "black@0.8".to_string(),
             x: "(w-text_w)/2".to_string(),
             y: "(h-text_h)/2".to_string(),
+            wrap_width: 60,
         }
     }
 }
@@ -36,11 +40,11 @@ pub fn drawtext_filter(
     // Replace special characters with escaped version
     let text = sanitize_shell_characters(text).replace('\n', "");
     // Wrap text to max width
-    let text = wrap_text(&text, 60);
+    let text = wrap_text(&text, options.wrap_width);
 
     let options = [
         // Font settings
-        ("font", &format!("{}", options.font)),
+        ("font", &options.font),
         ("fontcolor", &options.fontcolor),
         ("fontsize", &options.fontsize.to_string()),
         // Text background
@@ -55,8 +59,8 @@ pub fn drawtext_filter(
             "enable",
             &format!(
                 "'between(t, {}, {})'",
-                start.as_secs_f32(),
-                end.as_secs_f32()
+                format_seconds(start),
+                format_seconds(end)
             ),
         ),
         // Prevent special characters in text from breaking command
@@ -92,7 +96,7 @@ fn wrap_text(text: &str, max_width: usize) -> String {
             // Convert to string
             let mut chunk = chunk.iter().collect::<String>();
             // Add dash, if not final chunk
-            if i + 1 <= chunk_count {
+            if i < chunk_count {
                 chunk.push('-');
             }
             // Add to words
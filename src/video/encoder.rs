@@ -0,0 +1,260 @@
+use serde::Deserialize;
+
+use crate::config;
+
+/// Named quality preset, providing defaults for encoder settings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Quality {
+    /// Fast, low quality encode, for checking results
+    Draft,
+    /// Balanced speed and quality
+    #[default]
+    Standard,
+    /// Slow, high quality encode, for uploading
+    Upload,
+}
+
+/// Video codec, using software encoders only
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    X264,
+    X265,
+    Vp9,
+    Av1,
+}
+
+/// Container format of output file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Container {
+    Mp4,
+    Mkv,
+    Webm,
+}
+
+/// Encoder settings, with defaults from quality preset filled in
+#[derive(Debug)]
+pub struct Encoder {
+    pub video_codec: VideoCodec,
+    pub crf: u32,
+    pub bitrate: Option<String>,
+    pub preset: String,
+    pub pix_fmt: String,
+    pub fps: Option<f32>,
+    pub audio_codec: String,
+    pub audio_bitrate: String,
+    pub sample_rate: u32,
+    pub container: Option<Container>,
+}
+
+impl Encoder {
+    /// Resolve encoder settings from config, using quality preset for any missing settings
+    pub fn from_config(config: &config::Out) -> Self {
+        let defaults = Self::preset(config.quality);
+        let container = config
+            .container
+            .or_else(|| container_from_name(&config.name));
+
+        // WebM only supports VP8, VP9, or AV1 video, and Opus or Vorbis audio
+        let (default_video_codec, default_audio_codec) = match container {
+            Some(Container::Webm) => (VideoCodec::Vp9, "libopus".to_string()),
+            _ => (defaults.video_codec, defaults.audio_codec),
+        };
+        let video_codec = config.video_codec.unwrap_or(default_video_codec);
+
+        Self {
+            video_codec,
+            crf: config.crf.unwrap_or(defaults.crf),
+            bitrate: config.bitrate.clone(),
+            preset: config
+                .preset
+                .clone()
+                .unwrap_or_else(|| default_speed(video_codec, config.quality).to_string()),
+            pix_fmt: config.pix_fmt.clone().unwrap_or(defaults.pix_fmt),
+            fps: config.fps.or(defaults.fps),
+            audio_codec: config.audio_codec.clone().unwrap_or(default_audio_codec),
+            audio_bitrate: config
+                .audio_bitrate
+                .clone()
+                .unwrap_or(defaults.audio_bitrate),
+            sample_rate: config.sample_rate.unwrap_or(defaults.sample_rate),
+            container,
+        }
+    }
+
+    /// Default settings of quality preset
+    fn preset(quality: Quality) -> Self {
+        let (crf, fps, audio_bitrate, sample_rate) = match quality {
+            Quality::Draft => (32, Some(24.0), "96k", 44100),
+            Quality::Standard => (23, None, "160k", 44100),
+            Quality::Upload => (18, None, "192k", 48000),
+        };
+        Self {
+            video_codec: VideoCodec::X264,
+            crf,
+            bitrate: None,
+            preset: default_speed(VideoCodec::X264, quality).to_string(),
+            pix_fmt: "yuv420p".to_string(),
+            fps,
+            audio_codec: "aac".to_string(),
+            audio_bitrate: audio_bitrate.to_string(),
+            sample_rate,
+            container: None,
+        }
+    }
+
+    /// Fast, low quality settings for preview renders
+    pub fn preview(config: &config::Preview) -> Self {
+        Self {
+            preset: "ultrafast".to_string(),
+            fps: Some(config.fps),
+            container: Some(Container::Mp4),
+            ..Self::preset(Quality::Draft)
+        }
+    }
+
+    /// Arguments for ffmpeg output
+    pub fn args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        let mut push = |key: &str, value: &str| {
+            args.push(key.to_string());
+            args.push(value.to_string());
+        };
+
+        push("-c:v", self.video_codec.encoder());
+
+        // Constant bitrate, or constant quality
+        match &self.bitrate {
+            Some(bitrate) => push("-b:v", bitrate),
+            None => {
+                push("-crf", &self.crf.to_string());
+                // VP9 requires zero bitrate for constant quality
+                if self.video_codec == VideoCodec::Vp9 {
+                    push("-b:v", "0");
+                }
+            }
+        }
+
+        // Speed preset
+        match self.video_codec {
+            VideoCodec::Vp9 => {
+                push("-deadline", "good");
+                push("-cpu-used", &self.preset);
+            }
+            _ => push("-preset", &self.preset),
+        }
+
+        push("-pix_fmt", &self.pix_fmt);
+        if let Some(fps) = self.fps {
+            push("-r", &fps.to_string());
+        }
+
+        push("-c:a", &self.audio_codec);
+        push("-b:a", &self.audio_bitrate);
+        push("-ar", &self.sample_rate.to_string());
+
+        match self.container {
+            Some(Container::Mp4) | None => {
+                // Allow playback to start before file is fully downloaded
+                push("-movflags", "+faststart");
+            }
+            _ => (),
+        }
+        if let Some(container) = self.container {
+            push("-f", container.format());
+        }
+
+        args
+    }
+}
+
+impl VideoCodec {
+    /// Name of ffmpeg encoder
+    pub fn encoder(self) -> &'static str {
+        match self {
+            Self::X264 => "libx264",
+            Self::X265 => "libx265",
+            Self::Vp9 => "libvpx-vp9",
+            Self::Av1 => "libsvtav1",
+        }
+    }
+}
+
+impl Container {
+    /// Name of ffmpeg muxer
+    fn format(self) -> &'static str {
+        match self {
+            Self::Mp4 => "mp4",
+            Self::Mkv => "matroska",
+            Self::Webm => "webm",
+        }
+    }
+}
+
+/// Speed preset of codec for quality preset, in format used by encoder
+fn default_speed(codec: VideoCodec, quality: Quality) -> &'static str {
+    match (codec, quality) {
+        (VideoCodec::X264 | VideoCodec::X265, Quality::Draft) => "ultrafast",
+        (VideoCodec::X264 | VideoCodec::X265, Quality::Standard) => "medium",
+        (VideoCodec::X264 | VideoCodec::X265, Quality::Upload) => "slow",
+        (VideoCodec::Vp9, Quality::Draft) => "8",
+        (VideoCodec::Vp9, Quality::Standard) => "4",
+        (VideoCodec::Vp9, Quality::Upload) => "1",
+        (VideoCodec::Av1, Quality::Draft) => "12",
+        (VideoCodec::Av1, Quality::Standard) => "8",
+        (VideoCodec::Av1, Quality::Upload) => "4",
+    }
+}
+
+/// Guess container from file extension of output name
+fn container_from_name(name: &str) -> Option<Container> {
+    let (_, extension) = name.rsplit_once('.')?;
+    match extension.to_lowercase().as_str() {
+        "mp4" => Some(Container::Mp4),
+        "mkv" => Some(Container::Mkv),
+        "webm" => Some(Container::Webm),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Value following option in arguments
+    fn arg<'a>(args: &'a [String], key: &str) -> Option<&'a str> {
+        let i = args.iter().position(|arg| arg == key)?;
+        args.get(i + 1).map(String::as_str)
+    }
+
+    #[test]
+    fn args_match_container() {
+        let args_for = |name: &str| {
+            let config = config::Out {
+                name: name.to_string(),
+                ..Default::default()
+            };
+            Encoder::from_config(&config).args()
+        };
+
+        let args = args_for("video.mp4");
+        assert_eq!(arg(&args, "-c:v"), Some("libx264"));
+        assert_eq!(arg(&args, "-c:a"), Some("aac"));
+        assert_eq!(arg(&args, "-movflags"), Some("+faststart"));
+        assert_eq!(arg(&args, "-f"), Some("mp4"));
+
+        let args = args_for("video.mkv");
+        assert_eq!(arg(&args, "-c:v"), Some("libx264"));
+        assert_eq!(arg(&args, "-movflags"), None);
+        assert_eq!(arg(&args, "-f"), Some("matroska"));
+
+        let args = args_for("video.webm");
+        assert_eq!(arg(&args, "-c:v"), Some("libvpx-vp9"));
+        assert_eq!(arg(&args, "-c:a"), Some("libopus"));
+        assert_eq!(arg(&args, "-b:v"), Some("0"));
+        assert_eq!(arg(&args, "-cpu-used"), Some("4"));
+        assert_eq!(arg(&args, "-f"), Some("webm"));
+    }
+}
@@ -0,0 +1,260 @@
+use std::fmt;
+
+/// Amount of lines of ffmpeg log to attach to error
+const LOG_TAIL_LINES: usize = 20;
+
+/// Failure of ffmpeg command, with its category, command line, and end of its log
+#[derive(Debug)]
+pub struct FFMpegError {
+    pub kind: FFMpegErrorKind,
+    /// Full command line that was run
+    pub command: String,
+    /// Last lines of ffmpeg log
+    pub log_tail: String,
+}
+
+/// Category of ffmpeg failure
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FFMpegErrorKind {
+    /// Input file does not exist
+    MissingInput { path: String },
+    /// Font of text could not be found or loaded
+    UnknownFont { font: String },
+    /// Filter graph could not be parsed or initialized
+    FilterParse {
+        /// Name of filter, such as `drawtext`
+        filter: Option<String>,
+        /// Index of filter in filter graph
+        index: Option<usize>,
+        /// Index of text frame that created filter
+        frame: Option<usize>,
+    },
+    /// Encoder or decoder is not available in ffmpeg build
+    CodecNotFound { codec: String },
+    /// No space left to write output
+    DiskFull,
+    /// Command could not be started
+    Spawn { reason: String },
+    /// Any other failure, with exit code if available
+    Other { code: Option<i32> },
+}
+
+impl FFMpegError {
+    /// Categorize failure from ffmpeg log
+    pub fn from_log(command: String, log: &str, code: Option<i32>) -> Self {
+        let lines: Vec<_> = log.lines().collect();
+        let log_tail = lines[lines.len().saturating_sub(LOG_TAIL_LINES)..].join("\n");
+
+        Self {
+            kind: FFMpegErrorKind::from_log(log).unwrap_or(FFMpegErrorKind::Other { code }),
+            command,
+            log_tail,
+        }
+    }
+}
+
+impl FFMpegErrorKind {
+    /// Find first recognized failure in ffmpeg log
+    ///
+    /// Categories are checked in order of specificity, as some failures are followed by more
+    /// generic messages (such as a missing font causing a filter to fail to initialize)
+    fn from_log(log: &str) -> Option<Self> {
+        if log.contains("No space left on device") {
+            return Some(Self::DiskFull);
+        }
+
+        for line in log.lines() {
+            for prefix in ["Unknown encoder '", "Unknown decoder '"] {
+                if let Some(codec) = quoted_after(line, prefix) {
+                    return Some(Self::CodecNotFound { codec });
+                }
+            }
+            if line.contains("Encoder not found") || line.contains("Decoder not found") {
+                return Some(Self::CodecNotFound {
+                    codec: line.trim().to_string(),
+                });
+            }
+        }
+
+        for line in log.lines() {
+            if let Some(font) = quoted_after(line, "Could not load font \"") {
+                return Some(Self::UnknownFont { font });
+            }
+            if let Some((_, font)) = line.split_once("Cannot find a valid font for the family ") {
+                return Some(Self::UnknownFont {
+                    font: font.trim().to_string(),
+                });
+            }
+        }
+
+        let is_filter_error = [
+            "Error initializing filter",
+            "Error parsing filterchain",
+            "Error parsing a filter description",
+            "Error applying option",
+            "No such filter",
+        ]
+        .into_iter()
+        .any(|message| log.contains(message));
+        if is_filter_error {
+            let parsed = log.lines().find_map(parsed_filter);
+            let filter = parsed
+                .as_ref()
+                .map(|(name, _)| name.clone())
+                .or_else(|| log.lines().find_map(|line| quoted_after(line, "filter '")))
+                .or_else(|| {
+                    log.lines()
+                        .find_map(|line| quoted_after(line, "No such filter: '"))
+                });
+            return Some(Self::FilterParse {
+                filter,
+                index: parsed.map(|(_, index)| index),
+                frame: None,
+            });
+        }
+
+        // ffmpeg 6.1 and later name the input on its own line, after
+        // `[in#0 @ 0x...] Error opening input: No such file or directory`
+        if log.contains("Error opening input: No such file or directory") {
+            let path = log.lines().find_map(|line| {
+                let path = line.trim().strip_prefix("Error opening input file ")?;
+                Some(path.strip_suffix('.').unwrap_or(path))
+            });
+            if let Some(path) = path {
+                return Some(Self::MissingInput {
+                    path: path.to_string(),
+                });
+            }
+        }
+        // Older versions prefix the error with the path
+        for line in log.lines() {
+            let Some(path) = line.trim().strip_suffix(": No such file or directory") else {
+                continue;
+            };
+            if !path.starts_with('[') && !path.starts_with("Error opening input") {
+                return Some(Self::MissingInput {
+                    path: path.to_string(),
+                });
+            }
+        }
+
+        None
+    }
+}
+
+impl fmt::Display for FFMpegErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingInput { path } => write!(f, "Input file not found: {path}"),
+            Self::UnknownFont { font } => write!(f, "Font not found: {font}"),
+            Self::FilterParse {
+                filter,
+                index,
+                frame,
+            } => {
+                write!(f, "Failed to parse filter")?;
+                if let Some(filter) = filter {
+                    write!(f, " '{filter}'")?;
+                }
+                if let Some(index) = index {
+                    write!(f, " (filter #{index} of graph)")?;
+                }
+                if let Some(frame) = frame {
+                    write!(f, ", created by text frame {frame}")?;
+                }
+                Ok(())
+            }
+            Self::CodecNotFound { codec } => write!(f, "Codec not available: {codec}"),
+            Self::DiskFull => write!(f, "No space left on device"),
+            Self::Spawn { reason } => write!(f, "Failed to run ffmpeg: {reason}"),
+            Self::Other { code: Some(code) } => write!(f, "ffmpeg exited with code {code}"),
+            Self::Other { code: None } => write!(f, "ffmpeg was terminated"),
+        }
+    }
+}
+
+impl fmt::Display for FFMpegError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "FFMPEG Error: {}", self.kind)?;
+        writeln!(f, "Command: {}", self.command)?;
+        if !self.log_tail.is_empty() {
+            writeln!(f, "Log:")?;
+            write!(f, "{}", self.log_tail)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for FFMpegError {}
+
+/// Get text after prefix, until closing quote (same as last character of prefix)
+fn quoted_after(line: &str, prefix: &str) -> Option<String> {
+    let quote = prefix.chars().last()?;
+    let (_, rest) = line.split_once(prefix)?;
+    let (quoted, _) = rest.split_once(quote)?;
+    Some(quoted.to_string())
+}
+
+/// Get name and index of filter from log context, such as `[Parsed_drawtext_3 @ 0x...]`
+fn parsed_filter(line: &str) -> Option<(String, usize)> {
+    let (_, rest) = line.split_once("[Parsed_")?;
+    let (name, _) = rest.split_once(" @")?;
+    let (name, index) = name.rsplit_once('_')?;
+    Some((name.to_string(), index.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_log_works() {
+        assert_eq!(
+            FFMpegErrorKind::from_log("background.mp4: No such file or directory"),
+            Some(FFMpegErrorKind::MissingInput {
+                path: "background.mp4".to_string()
+            })
+        );
+        assert_eq!(
+            FFMpegErrorKind::from_log(concat!(
+                "[in#0 @ 0x55d0c1e3c9c0] Error opening input: No such file or directory\n",
+                "Error opening input file background.mp4.\n",
+                "Error opening input files: No such file or directory\n",
+            )),
+            Some(FFMpegErrorKind::MissingInput {
+                path: "background.mp4".to_string()
+            })
+        );
+        assert_eq!(
+            FFMpegErrorKind::from_log(concat!(
+                "[Parsed_drawtext_4 @ 0x5581] Could not load font \"font.ttf\": cannot open resource\n",
+                "[AVFilterGraph @ 0x5580] Error initializing filter 'drawtext' with args 'font=...'\n",
+            )),
+            Some(FFMpegErrorKind::UnknownFont {
+                font: "font.ttf".to_string()
+            })
+        );
+        assert_eq!(
+            FFMpegErrorKind::from_log(concat!(
+                "[Parsed_drawtext_4 @ 0x5581] Both text and text file provided.\n",
+                "[AVFilterGraph @ 0x5580] Error initializing filter 'drawtext' with args 'font=...'\n",
+            )),
+            Some(FFMpegErrorKind::FilterParse {
+                filter: Some("drawtext".to_string()),
+                index: Some(4),
+                frame: None,
+            })
+        );
+        assert_eq!(
+            FFMpegErrorKind::from_log("Unknown encoder 'libsvtav1'"),
+            Some(FFMpegErrorKind::CodecNotFound {
+                codec: "libsvtav1".to_string()
+            })
+        );
+        assert_eq!(
+            FFMpegErrorKind::from_log("av_interleaved_write_frame(): No space left on device"),
+            Some(FFMpegErrorKind::DiskFull)
+        );
+        assert_eq!(FFMpegErrorKind::from_log("Conversion failed!"), None);
+    }
+}
@@ -1,43 +1,106 @@
-use std::{ffi::OsStr, process};
+use std::{
+    ffi::OsStr,
+    io::{BufRead, BufReader, Read},
+    process::{self, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{
+    error::{FFMpegError, FFMpegErrorKind},
+    progress::Progress,
+};
 
 pub struct FFMpegCommand(process::Command);
 
 impl FFMpegCommand {
-    /// Create ffmpeg command
-    pub fn new(overwrite: bool) -> Self {
-        let mut cmd = process::Command::new("ffmpeg");
+    /// Create ffmpeg command, running executable at `path`
+    pub fn new(path: &str, overwrite: bool) -> Self {
+        let mut cmd = process::Command::new(path);
         // cmd.args(["-loglevel", "warning"]);
         if overwrite {
             cmd.arg("-y");
         }
+        // Never ask on stdin, such as whether to overwrite a file, as stderr is not shown
+        cmd.arg("-nostdin");
+        // Write machine-readable progress to stdout, instead of stats to stderr
+        cmd.args(["-progress", "pipe:1", "-nostats"]);
         Self(cmd)
     }
 
-    /// Run ffmpeg command
-    pub fn run(self) {
+    /// Run ffmpeg command, calling `on_progress` as output is rendered
+    ///
+    /// `total` is the expected duration of output, used to calculate progress fraction
+    pub fn run(
+        self,
+        total: Option<Duration>,
+        mut on_progress: impl FnMut(&Progress),
+    ) -> Result<(), FFMpegError> {
+        let command = self.command_line();
         let mut cmd = self.0;
 
-        let result = cmd.output().expect("Failed to run ffmpeg command");
+        let mut child = match cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn() {
+            Ok(child) => child,
+            Err(err) => {
+                return Err(FFMpegError {
+                    kind: FFMpegErrorKind::Spawn {
+                        reason: err.to_string(),
+                    },
+                    command,
+                    log_tail: String::new(),
+                })
+            }
+        };
+
+        // Read log in background, so ffmpeg does not block on a full pipe
+        let mut stderr = child.stderr.take().expect("Failed to read ffmpeg stderr");
+        let log = thread::spawn(move || {
+            let mut log = String::new();
+            stderr.read_to_string(&mut log).ok();
+            log
+        });
 
-        if !result.status.success() {
-            eprintln!("FFMPEG Error");
-            eprintln!("{}", String::from_utf8_lossy(&result.stderr));
-            std::process::exit(1);
-        } else {
-            println!("\x1b[1mSuccess!\x1b[0m");
+        let start = Instant::now();
+        let mut progress = Progress {
+            total,
+            ..Default::default()
+        };
+        let stdout = child.stdout.take().expect("Failed to read ffmpeg stdout");
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if progress.parse_line(&line) {
+                progress.elapsed = start.elapsed();
+                on_progress(&progress);
+            }
         }
+
+        let status = child.wait().expect("Failed to wait for ffmpeg command");
+        let log = log.join().unwrap_or_default();
+
+        if !status.success() {
+            return Err(FFMpegError::from_log(command, &log, status.code()));
+        }
+        println!("\x1b[1mSuccess!\x1b[0m");
+        Ok(())
     }
 
     /// Print full command to stdout
     pub fn show_command(&self) {
-        println!(
+        println!("{}", self.command_line());
+    }
+
+    /// Full command, as a single line
+    pub fn command_line(&self) -> String {
+        format!(
             "ffmpeg {}",
             self.0
                 .get_args()
                 .map(|x| x.to_string_lossy())
                 .collect::<Vec<_>>()
                 .join(" ")
-        );
+        )
     }
 }
 
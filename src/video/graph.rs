@@ -0,0 +1,134 @@
+/// Filter graph of a single video stream, with other inputs overlaid onto it
+///
+/// Filters are chained until an overlay is needed, which starts a new chain
+pub struct VideoGraph {
+    /// Completed chains
+    chains: Vec<String>,
+    /// Filters of current chain
+    filters: Vec<String>,
+    /// Input labels of current chain
+    head: String,
+    /// Amount of intermediate labels created
+    count: usize,
+}
+
+/// Find filter at index of filter graph script, counting filters of all chains in order
+///
+/// This matches the numbering ffmpeg uses in its log, such as `Parsed_drawtext_3`.
+/// Quoted and escaped separators are skipped, as ffmpeg does when parsing.
+pub fn filter_at(script: &str, index: usize) -> Option<&str> {
+    split_filters(script).get(index).copied()
+}
+
+/// Names of all filters of filter graph script, in order, without duplicates
+pub fn filter_names(script: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    for filter in split_filters(script) {
+        // Skip input labels, such as `[0:v][v0]`
+        let mut rest = filter.trim();
+        while let Some(label_end) = rest.strip_prefix('[').and_then(|rest| rest.find(']')) {
+            rest = &rest[label_end + 2..];
+        }
+        let name: String = rest
+            .chars()
+            .take_while(|char| char.is_ascii_alphanumeric() || *char == '_')
+            .collect();
+        if !name.is_empty() && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// Split filter graph script into filters, skipping quoted and escaped separators
+fn split_filters(script: &str) -> Vec<&str> {
+    let mut filters = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for (i, char) in script.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match char {
+            '\\' if !quoted => escaped = true,
+            '\'' => quoted = !quoted,
+            ',' | ';' if !quoted => {
+                filters.push(&script[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    filters.push(&script[start..]);
+    filters
+}
+
+impl VideoGraph {
+    /// Create graph, starting from stream specifier (such as `0:v`)
+    pub fn new(input: &str) -> Self {
+        Self {
+            chains: Vec::new(),
+            filters: Vec::new(),
+            head: format!("[{input}]"),
+            count: 0,
+        }
+    }
+
+    /// Add filter to end of current chain
+    pub fn push(&mut self, filter: impl Into<String>) {
+        self.filters.push(filter.into());
+    }
+
+    /// Overlay another stream onto current stream, with options of `overlay` filter
+    pub fn overlay(&mut self, input: &str, options: &str) {
+        let label = format!("[vg{}]", self.count);
+        self.count += 1;
+
+        self.chains
+            .push(format!("{}{}{label}", self.head, self.chain_filters()));
+        self.filters = vec![format!("overlay={options}")];
+        self.head = format!("{label}[{input}]");
+    }
+
+    /// Complete graph, with final stream labelled as `output`
+    pub fn finish(mut self, output: &str) -> String {
+        self.chains
+            .push(format!("{}{}[{output}]", self.head, self.chain_filters()));
+        self.chains.join(";")
+    }
+
+    /// Filters of current chain, joined, or a no-op filter if empty
+    fn chain_filters(&self) -> String {
+        if self.filters.is_empty() {
+            "null".to_string()
+        } else {
+            self.filters.join(",")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_at_works() {
+        let script = "[0:a]apad[a];[0:v]scale=1280:720,drawtext=text='a, b; c':x=1[v0];[v0][1:v]overlay=x=(W-w)/2[v]";
+        assert_eq!(filter_at(script, 0), Some("[0:a]apad[a]"));
+        assert_eq!(filter_at(script, 1), Some("[0:v]scale=1280:720"));
+        assert_eq!(
+            filter_at(script, 2),
+            Some("drawtext=text='a, b; c':x=1[v0]")
+        );
+        assert_eq!(filter_at(script, 3), Some("[v0][1:v]overlay=x=(W-w)/2[v]"));
+        assert_eq!(filter_at(script, 4), None);
+
+        assert_eq!(
+            filter_names(script),
+            vec!["apad", "scale", "drawtext", "overlay"]
+        );
+    }
+}
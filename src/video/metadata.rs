@@ -0,0 +1,250 @@
+use serde::Serialize;
+use std::{fs, time::Duration};
+
+use super::{timeline, Output};
+use crate::{
+    config::{Config, MetadataFormat},
+    voice::Voice,
+    Text, TextKind,
+};
+
+/// Maximum length of Youtube title, in characters
+const MAX_TITLE_CHARS: usize = 100;
+/// Minimum length of Youtube chapter
+const MIN_CHAPTER_LENGTH: Duration = Duration::from_secs(10);
+/// Minimum amount of chapters for Youtube to show them
+const MIN_CHAPTER_COUNT: usize = 3;
+
+/// Upload metadata of video
+#[derive(Debug, Serialize)]
+pub struct Metadata {
+    pub title: String,
+    pub description: String,
+    pub tags: Vec<String>,
+    pub chapters: Vec<Chapter>,
+}
+
+/// Chapter of video, starting at a post or comment
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Chapter {
+    /// Start time, formatted as `m:ss`
+    pub time: String,
+    /// Start time, in seconds
+    pub start: f32,
+    pub title: String,
+}
+
+/// Create upload metadata from posts and comments of part of video, and its timeline
+///
+/// Title and source link come from `post`, the first post of whole video, as later parts of
+/// comments may not include it
+pub fn metadata(
+    config: &Config,
+    post: Option<&Text>,
+    voices: &[Voice],
+    part: Option<usize>,
+) -> Metadata {
+    let config_metadata = &config.metadata;
+
+    let source = post.and_then(|post| post.source.as_ref());
+
+    let mut title = config_metadata
+        .title
+        .replace("{title}", post.map_or("", |post| post.content.as_str()))
+        .replace("{subreddit}", source.map_or("", |source| &source.subreddit))
+        .replace("{author}", source.map_or("", |source| &source.author));
+    if let Some(part) = part {
+        title += &format!(" - Part {part}");
+    }
+    if title.chars().count() > MAX_TITLE_CHARS {
+        title = title.chars().take(MAX_TITLE_CHARS - 3).collect::<String>() + "...";
+    }
+
+    let chapters = chapters(config, voices);
+
+    let mut description = Vec::new();
+    if let Some(text) = &config_metadata.description {
+        description.push(text.clone());
+    }
+
+    let mut links: Vec<String> = source
+        .and_then(|source| source.link.clone())
+        .into_iter()
+        .collect();
+    let mut authors = Vec::new();
+    let mut tags = Vec::new();
+    for text in voices.iter().map(|voice| &voice.text) {
+        let Some(source) = &text.source else {
+            continue;
+        };
+        // Link to post, from its title
+        if let (TextKind::Title, Some(link)) = (text.kind, &source.link) {
+            if !links.contains(link) {
+                links.push(link.clone());
+            }
+        }
+        let author = format!("u/{}", source.author);
+        if source.author != "[deleted]" && !authors.contains(&author) {
+            authors.push(author);
+        }
+
+        tags.push(source.subreddit.to_lowercase());
+        if let Some(flair) = &source.flair {
+            tags.push(flair.to_lowercase());
+        }
+    }
+
+    if !links.is_empty() {
+        let links: Vec<_> = links
+            .iter()
+            .map(|link| format!("https://www.reddit.com{link}"))
+            .collect();
+        description.push(format!("Source:\n{}", links.join("\n")));
+    }
+    if !authors.is_empty() {
+        description.push(format!("Credits:\n{}", authors.join("\n")));
+    }
+
+    // Youtube only shows chapters if there are enough, and none are too short
+    let mut description_chapters: Vec<&Chapter> = Vec::new();
+    for chapter in &chapters {
+        let too_short = description_chapters
+            .last()
+            .is_some_and(|last| chapter.start - last.start < MIN_CHAPTER_LENGTH.as_secs_f32());
+        if !too_short {
+            description_chapters.push(chapter);
+        }
+    }
+    if description_chapters.len() >= MIN_CHAPTER_COUNT {
+        let lines: Vec<_> = description_chapters
+            .iter()
+            .map(|chapter| format!("{} {}", chapter.time, chapter.title))
+            .collect();
+        description.push(format!("Chapters:\n{}", lines.join("\n")));
+    }
+
+    tags.push("reddit".to_string());
+    tags.extend(config_metadata.tags.iter().cloned());
+    let mut unique_tags = Vec::new();
+    for tag in tags {
+        if !tag.is_empty() && !unique_tags.contains(&tag) {
+            unique_tags.push(tag);
+        }
+    }
+
+    Metadata {
+        title,
+        description: description.join("\n\n"),
+        tags: unique_tags,
+        chapters,
+    }
+}
+
+/// Save upload metadata next to output file, as JSON or YAML
+///
+/// Returns path of metadata file
+pub fn save_metadata(
+    config: &Config,
+    post: Option<&Text>,
+    voices: &[Voice],
+    output: &Output,
+) -> Result<String, String> {
+    let metadata = metadata(config, post, voices, output.part);
+
+    let (extension, text) = match config.metadata.format {
+        MetadataFormat::Json => (
+            "json",
+            serde_json::to_string_pretty(&metadata).map_err(|err| err.to_string())?,
+        ),
+        MetadataFormat::Yaml => (
+            "yaml",
+            serde_yaml::to_string(&metadata).map_err(|err| err.to_string())?,
+        ),
+    };
+    let path = output.sidecar(&format!("metadata.{extension}"));
+    fs::write(&path, text).map_err(|err| format!("Failed to save metadata - {err}"))?;
+    Ok(path)
+}
+
+/// Chapter at each post title and comment, with first chapter starting at beginning of video
+fn chapters(config: &Config, voices: &[Voice]) -> Vec<Chapter> {
+    let mut chapters = Vec::new();
+    for (voice, slot) in voices.iter().zip(timeline(config, voices)) {
+        let text = &voice.text;
+        let title = match (text.kind, &text.source) {
+            (TextKind::Title, _) => text.content.clone(),
+            (TextKind::Comment, Some(source)) => format!("u/{}", source.author),
+            (TextKind::Comment, None) => "Comment".to_string(),
+            // Body belongs to chapter of title, and intro is included in first chapter
+            (TextKind::Body | TextKind::Intro, _) => continue,
+        };
+        let start = if chapters.is_empty() {
+            Duration::ZERO
+        } else {
+            slot.start
+        };
+        chapters.push(Chapter {
+            time: chapter_timestamp(start),
+            start: start.as_secs_f32(),
+            title,
+        });
+    }
+    chapters
+}
+
+/// Format timestamp of chapter, as `m:ss`, or `h:mm:ss` if longer than an hour
+fn chapter_timestamp(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chapter_timestamp_works() {
+        assert_eq!(chapter_timestamp(Duration::ZERO), "0:00");
+        assert_eq!(chapter_timestamp(Duration::from_millis(65_900)), "1:05");
+        assert_eq!(chapter_timestamp(Duration::from_secs(3_725)), "1:02:05");
+    }
+
+    #[test]
+    fn later_parts_use_first_post() {
+        let text = |kind, content: &str, link: Option<&str>| Text {
+            kind,
+            content: content.to_string(),
+            source: Some(crate::Source {
+                subreddit: "AskReddit".to_string(),
+                author: "someone".to_string(),
+                score: 1,
+                link: link.map(String::from),
+                flair: None,
+            }),
+        };
+        let post = text(
+            TextKind::Title,
+            "Question?",
+            Some("/r/AskReddit/comments/abc/"),
+        );
+        let comment = Voice {
+            text: text(TextKind::Comment, "Answer", None),
+            bytes: Vec::new(),
+            duration: Duration::from_secs(5),
+            estimated: true,
+        };
+
+        let mut config = Config::default();
+        config.metadata.title = "{title} (r/{subreddit})".to_string();
+        let metadata = metadata(&config, Some(&post), &[comment], Some(2));
+        assert_eq!(metadata.title, "Question? (r/AskReddit) - Part 2");
+        assert!(metadata
+            .description
+            .contains("https://www.reddit.com/r/AskReddit/comments/abc/"));
+    }
+}
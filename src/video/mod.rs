@@ -1,86 +1,391 @@
+mod background;
 mod drawtext;
+mod encoder;
+mod error;
 mod ffmpeg;
+mod graph;
+mod metadata;
+mod music;
+mod output;
+mod parts;
+mod plan;
+mod preflight;
+mod preview;
+mod probe;
+mod profile;
+mod progress;
+mod thumbnail;
+mod timeline;
 
 use std::{fs, time::Duration};
 
 use self::drawtext::{drawtext_filter, DrawtextOptions};
 use self::ffmpeg::FFMpegCommand;
-use crate::{config::Config, voice::Voice};
-
-pub fn concat_voices(config: &Config, dir: &str) {
-    let mut ffmpeg = FFMpegCommand::new(config.out.overwrite);
+use self::graph::{filter_at, VideoGraph};
+use self::music::music_filter;
+use self::preview::preview_window;
+use crate::{
+    card,
+    config::{self, Config},
+    reddit::format_number,
+    voice::Voice,
+    Text, TextKind,
+};
+
+pub use self::background::{choose_background, seeded_rng, Background};
+pub use self::encoder::{Container, Encoder, Quality, VideoCodec};
+pub use self::error::{FFMpegError, FFMpegErrorKind};
+pub use self::metadata::{metadata, save_metadata, Chapter, Metadata};
+pub use self::music::choose_music;
+pub use self::output::{
+    resolve_collision, resolve_output, save_manifest, Collision, ManifestEntry, Output,
+};
+pub use self::parts::{max_duration, split_parts};
+pub use self::plan::{plan, Plan, PlannedFrame};
+pub use self::preflight::{preflight, Tools};
+pub use self::preview::{render_preview, save_thumbnails};
+pub use self::probe::{probe, MediaInfo, VideoStream};
+pub use self::profile::{Fit, Layout, Profile};
+pub use self::progress::{print_progress, Progress};
+pub use self::thumbnail::create_thumbnail;
+pub use self::timeline::{duration_from_secs, format_seconds, timeline, total_duration, Slot};
+
+/// Time to show 'Part N' title card at start of each part
+const PART_CARD_TIME: Duration = Duration::from_secs(3);
+
+/// Time to show outro after all text frames
+pub fn outro_duration(config: &Config) -> Duration {
+    // Old `[outro] length` is still accepted
+    duration_from_secs(config.outro.length.unwrap_or(config.out.outro))
+}
 
-    // Filter: concatenate
-    ffmpeg.args(["-f", "concat"]);
+/// Save chosen background next to output file, so it can be reused or avoided later
+///
+/// Returns path of saved file
+pub fn save_background(output: &Output, background: &Background) -> String {
+    let path = output.sidecar("background.json");
+    let json = serde_json::to_string_pretty(background).expect("Failed to serialize background");
+    fs::write(&path, json).expect("Failed to save background record");
+    path
+}
 
-    // Input: text file of audio file paths
-    ffmpeg.args(["-i", &format!("{dir}/voices.txt")]);
+/// Format of all audio segments, so they can be concatenated
+const AUDIO_FORMAT: &str = "aformat=sample_fmts=fltp:sample_rates=44100:channel_layouts=stereo";
+
+/// Add voice and transition inputs to command, and create filters which concatenate them with
+/// pauses, outputting `[voices]`
+fn audio_filters(
+    config: &Config,
+    dir: &str,
+    voices: &[Voice],
+    ffmpeg: &mut FFMpegCommand,
+    input_count: &mut usize,
+) -> Vec<String> {
+    // Input: audio file of each voice, or silence if voice has no audio
+    let sources: Vec<_> = voices
+        .iter()
+        .enumerate()
+        .map(|(i, voice)| {
+            if voice.is_silent() {
+                let duration = format_seconds(voice.duration);
+                return format!(
+                    "anullsrc=channel_layout=stereo:sample_rate=44100,atrim=0:{duration},"
+                );
+            }
+            ffmpeg.args(["-i", &format!("{dir}/audio/{i}.mp3")]);
+            *input_count += 1;
+            format!("[{}:a]", *input_count - 1)
+        })
+        .collect();
+
+    let transitions: Vec<_> = (0..voices.len())
+        .filter(|i| timeline::has_transition(config, voices, *i))
+        .collect();
 
-    // Copy data without re-encoding
-    ffmpeg.args(["-c", "copy"]);
+    let mut filters = Vec::new();
 
-    // Output path
-    ffmpeg.arg(format!("{dir}/audio.mp3"));
+    // Input: transition sound, split for each pause it is played in
+    if let Some(transition) = &config.pause.transition {
+        if !transitions.is_empty() {
+            ffmpeg.args(["-i", transition]);
+            let outputs: String = transitions.iter().map(|i| format!("[t{i}]")).collect();
+            filters.push(format!(
+                "[{input_count}:a]asplit={}{outputs}",
+                transitions.len()
+            ));
+            *input_count += 1;
+        }
+    }
 
-    ffmpeg.show_command();
-    ffmpeg.run();
-}
+    // Segments of audio: leading silence, then each voice followed by pause
+    let mut segments = String::new();
+    let mut segment_count = voices.len() + transitions.len();
 
-pub fn apply_video_audio(config: &Config, dir: &str) {
-    let mut ffmpeg = FFMpegCommand::new(config.out.overwrite);
+    let leading = timeline::leading_silence(config);
+    if !leading.is_zero() {
+        let leading = format_seconds(leading);
+        filters.push(format!(
+            "anullsrc=channel_layout=stereo:sample_rate=44100,atrim=0:{leading},{AUDIO_FORMAT}[leading]"
+        ));
+        segments += "[leading]";
+        segment_count += 1;
+    }
 
-    // Background video input
-    ffmpeg.args(["-i", &config.assets.background]);
-    // Concatenated audio
-    ffmpeg.args(["-i", &format!("{dir}/audio.mp3")]);
+    for (i, pause) in timeline::pauses(config, voices).iter().enumerate() {
+        let pause = format_seconds(*pause);
+        let source = &sources[i];
+
+        if transitions.contains(&i) {
+            // Pause is filled with transition sound, trimmed or padded to length of pause
+            filters.push(format!("{source}{AUDIO_FORMAT}[v{i}]"));
+            filters.push(format!(
+                "[t{i}]{AUDIO_FORMAT},atrim=0:{pause},apad=whole_dur={pause}[p{i}]"
+            ));
+            segments += &format!("[v{i}][p{i}]");
+        } else {
+            // Pause is silence added to end of voice
+            filters.push(format!("{source}{AUDIO_FORMAT},apad=pad_dur={pause}[v{i}]"));
+            segments += &format!("[v{i}]");
+        }
+    }
 
-    // Use audio file for video audio (mutes video and replaces audio)
-    ffmpeg.args(["-map", "0:v:0", "-map", "1:a:0"]);
+    // Concatenate all segments
+    filters.push(format!(
+        "{segments}concat=n={segment_count}:v=0:a=1[voices]"
+    ));
 
-    // Copy data without re-encoding
-    ffmpeg.args(["-c", "copy"]);
+    filters
+}
 
-    // Output path
-    ffmpeg.arg(format!("{dir}/video.mp4"));
+/// Render video in a single pass, from background, voices, and other assets
+///
+/// `on_progress` is called as video is rendered, such as with `print_progress`
+pub fn render_video(
+    config: &Config,
+    dir: &str,
+    voices: &[Voice],
+    background: &Background,
+    music: Option<&str>,
+    output: &Output,
+    on_progress: impl FnMut(&Progress),
+) -> Result<(), FFMpegError> {
+    let render = build_render(config, dir, voices, background, music, output, None);
+    render.show_command();
+    render.run(config, voices, on_progress)
+}
 
-    ffmpeg.show_command();
-    ffmpeg.run();
+/// ffmpeg command and filter script to render one video, built without writing or running anything
+pub struct Render {
+    command: FFMpegCommand,
+    /// Temporary directory of files read by command
+    pub dir: String,
+    /// Filter graph, joined into a script
+    pub script: String,
+    /// Length of output video
+    pub length: Duration,
+    /// Filters created by each text frame, to find frame of a failed filter
+    frame_filters: Vec<(usize, String)>,
+    /// Resolution of video, before scaling of preview, to fit card images in
+    frame_size: (u32, u32),
 }
 
-pub fn render_video(config: &Config, dir: &str, voices: &[Voice]) {
-    let mut ffmpeg = FFMpegCommand::new(config.out.overwrite);
+impl Render {
+    /// Full ffmpeg command, as a single line
+    pub fn command_line(&self) -> String {
+        self.command.command_line()
+    }
 
-    // Background video with voice audio
-    ffmpeg.args(["-i", &format!("{dir}/video.mp4")]);
+    /// Print full ffmpeg command to stdout
+    pub fn show_command(&self) {
+        self.command.show_command();
+    }
 
+    /// Write card images and filter script, then run ffmpeg command
+    ///
+    /// If a filter of a text frame fails to parse, the error is mapped to the index of that frame
+    pub fn run(
+        self,
+        config: &Config,
+        voices: &[Voice],
+        on_progress: impl FnMut(&Progress),
+    ) -> Result<(), FFMpegError> {
+        if config.cards.enabled {
+            let slots = timeline(config, voices);
+            save_cards(config, &self.dir, voices, &slots, self.frame_size);
+        }
+        fs::write(filter_script_path(&self.dir), &self.script)
+            .expect("Failed to write temporary filter file");
+
+        let Self {
+            command,
+            script,
+            length,
+            frame_filters,
+            ..
+        } = self;
+
+        command.run(Some(length), on_progress).map_err(|mut err| {
+            if let FFMpegErrorKind::FilterParse {
+                index: Some(index),
+                frame,
+                ..
+            } = &mut err.kind
+            {
+                *frame = filter_at(&script, *index).and_then(|failed| {
+                    frame_filters
+                        .iter()
+                        .find(|(_, filter)| failed.contains(filter.as_str()))
+                        .map(|(i, _)| *i)
+                });
+            }
+            err
+        })
+    }
+}
+
+/// Build command to render video from background, voices, and other assets
+///
+/// Voices are read from `{dir}/audio`, and card images and filter script are written to `dir`
+/// when run.
+/// If `preview` is given, a fast, low-resolution preview is rendered instead.
+pub fn build_render(
+    config: &Config,
+    dir: &str,
+    voices: &[Voice],
+    background: &Background,
+    music: Option<&str>,
+    output: &Output,
+    preview: Option<&config::Preview>,
+) -> Render {
+    // Collision of output file is resolved before rendering, which reserves it as empty file
+    let mut ffmpeg = FFMpegCommand::new(&config.ffmpeg.path, true);
+
+    let slots = timeline(config, voices);
+    let total_duration = total_duration(config, voices);
+    let outro_end = total_duration + outro_duration(config);
+
+    // Repeat background, if shorter than audio
+    ffmpeg.args(background.loop_args());
+    // Start background from offset
+    ffmpeg.args(["-ss", &format!("{:.3}", background.offset)]);
+    // Background video input
+    ffmpeg.args(["-i", &background.path]);
+    let mut input_count = 1;
+
+    // Voices, concatenated with pauses
+    let mut chains = audio_filters(config, dir, voices, &mut ffmpeg, &mut input_count);
+
+    if let Some(music) = music {
+        // Repeat music, if shorter than video
+        ffmpeg.args(["-stream_loop", "-1", "-i", music]);
+
+        // Mix music under voices
+        let music_input = format!("{input_count}:a");
+        chains.push(music_filter(
+            &config.music,
+            "voices",
+            &music_input,
+            outro_end,
+        ));
+        input_count += 1;
+    } else {
+        // Pad voices with silence, to cover outro
+        chains.push("[voices]apad[audio]".to_string());
+    }
+
+    let layout = config
+        .out
+        .profile
+        .layout_for(background.video.width, background.video.height);
     let drawtext_options = DrawtextOptions {
         font: "Serif".to_string(),
+        fontsize: layout.fontsize,
         box_: true,
+        x: layout.x.clone(),
+        y: layout.y.clone(),
+        wrap_width: layout.wrap_width,
         ..Default::default()
     };
 
-    let mut total_duration = Duration::ZERO;
-    let mut filters = Vec::new();
-    for voice in voices {
-        let Voice { text, duration, .. } = voice;
+    let mut graph = VideoGraph::new(&format!("0:{}", background.video.index));
+    // Filters created by each text frame, to find frame of a failed filter
+    let mut frame_filters = Vec::new();
 
-        let start = total_duration;
-        total_duration += *duration;
-        let end = total_duration;
+    // Scale and crop background to output resolution
+    if let Some(filter) = config.out.profile.scale_filter(config.out.fit) {
+        graph.push(filter);
+    }
+
+    if config.cards.enabled {
+        // Card images, shown in sequence
+        ffmpeg.args(["-f", "concat", "-safe", "0", "-i", &cards_list_path(dir)]);
 
-        let filter = drawtext_filter(&drawtext_options, text, start, end);
+        graph.overlay(
+            &format!("{input_count}:v"),
+            &format!("x=(W-w)/2:y={}:eof_action=pass", layout.overlay_y),
+        );
+        input_count += 1;
+    } else {
+        // Show each text frame until next frame starts
+        for (i, (voice, slot)) in voices.iter().zip(&slots).enumerate() {
+            if voice.text.kind == TextKind::Intro {
+                for filter in title_card_filters(&layout, &voice.text, slot.start, slot.end) {
+                    frame_filters.push((i, filter.clone()));
+                    graph.push(filter);
+                }
+                continue;
+            }
+
+            let filter =
+                drawtext_filter(&drawtext_options, &voice.text.content, slot.start, slot.end);
+
+            frame_filters.push((i, filter.clone()));
+            graph.push(filter);
+        }
+    }
+
+    // End-screen image, shown during outro
+    if let Some(image) = &config.outro.image {
+        ffmpeg.args(["-i", image]);
+
+        graph.overlay(
+            &format!("{input_count}:v"),
+            &format!(
+                "x=(W-w)/2:y=(H-h)/2:enable='between(t, {}, {})'",
+                format_seconds(total_duration),
+                format_seconds(outro_end)
+            ),
+        );
+    }
+
+    // Call-to-action text, shown during outro
+    if let Some(text) = &config.outro.text {
+        let drawtext_options = DrawtextOptions {
+            font: "Serif".to_string(),
+            fontsize: layout.fontsize * 3 / 2,
+            box_: true,
+            y: layout.title_y.clone(),
+            wrap_width: layout.wrap_width,
+            ..Default::default()
+        };
 
-        filters.push(filter);
+        graph.push(drawtext_filter(
+            &drawtext_options,
+            text,
+            total_duration,
+            outro_end,
+        ));
     }
 
     if let Some(watermark) = &config.assets.watermark {
         let drawtext_options = DrawtextOptions {
-            x: "w*0.8-text_w/2".to_string(),
-            y: "h*0.3-text_h/2".to_string(),
+            fontsize: layout.fontsize,
+            x: layout.watermark_x,
+            y: layout.watermark_y,
             ..Default::default()
         };
 
-        filters.push(drawtext_filter(
+        graph.push(drawtext_filter(
             &drawtext_options,
             watermark,
             Duration::ZERO,
@@ -88,43 +393,186 @@ pub fn render_video(config: &Config, dir: &str, voices: &[Voice]) {
         ));
     }
 
-    let filepath = format!("{dir}/filter.txt");
-    fs::write(&filepath, filters.join(",")).expect("Failed to write temporary filter file");
-    ffmpeg.args(["-filter_complex_script", &filepath]);
+    if let Some(part) = output.part {
+        let drawtext_options = DrawtextOptions {
+            font: "Serif".to_string(),
+            fontsize: layout.fontsize * 2,
+            box_: true,
+            y: layout.title_y,
+            ..Default::default()
+        };
+
+        graph.push(drawtext_filter(
+            &drawtext_options,
+            &format!("Part {part}"),
+            Duration::ZERO,
+            PART_CARD_TIME.min(total_duration),
+        ));
+    }
+
+    if let Some(preview) = preview {
+        // Reduce resolution, keeping dimensions even for encoder
+        let scale = preview.scale;
+        graph.push(format!("scale=trunc(iw*{scale}/2)*2:trunc(ih*{scale}/2)*2"));
+    }
+
+    chains.push(graph.finish("v"));
+
+    let script = chains.join(";");
+    ffmpeg.args(["-filter_complex_script", &filter_script_path(dir)]);
 
-    // Trim video to duration of all audio
-    const OUTRO_TIME: Duration = Duration::from_secs(2);
-    ffmpeg.args([
-        "-ss",
-        "00:00:00",
-        "-to",
-        &timestamp_from_duration(total_duration + OUTRO_TIME),
-    ]);
+    // Use rendered video and mixed audio (mutes background)
+    ffmpeg.args(["-map", "[v]", "-map", "[audio]"]);
 
-    // Output file
-    ffmpeg.arg(&config.out.name);
+    // Render only time window of preview
+    let (start, length) = match preview {
+        Some(preview) => preview_window(preview, total_duration, outro_end),
+        None => (Duration::ZERO, outro_end),
+    };
+    if !start.is_zero() {
+        ffmpeg.args(["-ss", &format_seconds(start)]);
+    }
+
+    // Stop looped background and padded audio after outro
+    ffmpeg.args(["-t", &format_seconds(length)]);
 
-    ffmpeg.show_command();
-    ffmpeg.run();
+    // Codecs and quality
+    let encoder = match preview {
+        Some(preview) => Encoder::preview(preview),
+        None => Encoder::from_config(&config.out),
+    };
+    ffmpeg.args(encoder.args());
+
+    // Output file, moved to its path once finished
+    ffmpeg.arg(output.partial_path());
+
+    Render {
+        command: ffmpeg,
+        dir: dir.to_string(),
+        script,
+        length,
+        frame_filters,
+        frame_size: config
+            .out
+            .profile
+            .resolution()
+            .unwrap_or((background.video.width, background.video.height)),
+    }
 }
 
-/// format timestamp (hh:mm:ss) from time in seconds
+/// Path of filter script in temporary directory
+fn filter_script_path(dir: &str) -> String {
+    format!("{dir}/filter.txt")
+}
+
+/// Path of card images list file in temporary directory
+fn cards_list_path(dir: &str) -> String {
+    format!("{dir}/cards.txt")
+}
+
+/// Filters to show title card of post, with subreddit and score above title
+fn title_card_filters(layout: &Layout, text: &Text, start: Duration, end: Duration) -> Vec<String> {
+    let mut filters = Vec::new();
+
+    let drawtext_options = DrawtextOptions {
+        font: "Serif".to_string(),
+        fontsize: layout.fontsize * 3 / 2,
+        box_: true,
+        x: layout.x.clone(),
+        y: layout.y.clone(),
+        wrap_width: layout.wrap_width * 2 / 3,
+        ..Default::default()
+    };
+    filters.push(drawtext_filter(
+        &drawtext_options,
+        &text.content,
+        start,
+        end,
+    ));
+
+    if let Some(source) = &text.source {
+        let drawtext_options = DrawtextOptions {
+            fontsize: layout.fontsize,
+            box_: true,
+            y: layout.title_y.clone(),
+            ..Default::default()
+        };
+        let heading = format!(
+            "r/{}  -  {} upvotes",
+            source.subreddit,
+            format_number(source.score).trim()
+        );
+        filters.push(drawtext_filter(&drawtext_options, &heading, start, end));
+    }
+
+    filters
+}
+
+/// Render card image of each text frame, and save list file for ffmpeg concat demuxer
+fn save_cards(
+    config: &Config,
+    dir: &str,
+    voices: &[Voice],
+    slots: &[Slot],
+    frame_size: (u32, u32),
+) {
+    let font = card::load_font(&config.cards.font).expect("Failed to load card font");
+    fs::create_dir_all(format!("{dir}/cards")).expect("Failed to create cards dir");
+
+    for (i, voice) in voices.iter().enumerate() {
+        let image = card::render_card(&config.cards, &font, &voice.text, frame_size);
+        image
+            .save(format!("{dir}/cards/{i}.png"))
+            .expect("Failed to save card image");
+    }
+    // Transparent image, shown during leading silence
+    image::RgbaImage::new(1, 1)
+        .save(format!("{dir}/cards/blank.png"))
+        .expect("Failed to save card image");
+
+    fs::write(cards_list_path(dir), cards_list(slots)).expect("Failed to save cards list file");
+}
+
+/// List file of card images for ffmpeg concat demuxer, with each card shown during its slot
 ///
-/// todo add milliseconds
+/// Blank image is shown before first slot, during leading silence
+fn cards_list(slots: &[Slot]) -> String {
+    let mut list_file = vec!["ffconcat version 1.0".to_string()];
+    if let Some(first) = slots.first().filter(|first| !first.start.is_zero()) {
+        list_file.push("file 'cards/blank.png'".to_string());
+        list_file.push(format!("duration {}", format_seconds(first.start)));
+    }
+    for (i, slot) in slots.iter().enumerate() {
+        list_file.push(format!("file 'cards/{i}.png'"));
+        list_file.push(format!(
+            "duration {}",
+            format_seconds(slot.end - slot.start)
+        ));
+    }
+    // Final file must be repeated, for its duration to be used
+    if !slots.is_empty() {
+        list_file.push(format!("file 'cards/{}.png'", slots.len() - 1));
+    }
+    list_file.join("\n")
+}
+
+/// Format timestamp (hh:mm:ss.mmm) from duration
 fn timestamp_from_duration(duration: Duration) -> String {
+    let millis = duration.subsec_millis();
     let mut seconds = duration.as_secs();
 
     let mut minutes = seconds / 60;
-    seconds = seconds % 60;
+    seconds %= 60;
 
     let hours = minutes / 60;
-    minutes = minutes % 60;
+    minutes %= 60;
 
     format!(
-        "{hh}:{mm}:{ss}",
+        "{hh}:{mm}:{ss}.{ms}",
         hh = hours,
         mm = leading_zeros(minutes, 2),
-        ss = leading_zeros(seconds, 2)
+        ss = leading_zeros(seconds, 2),
+        ms = leading_zeros(millis as u64, 3)
     )
 }
 
@@ -137,3 +585,57 @@ fn leading_zeros(number: u64, length: usize) -> String {
         number
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamp_from_duration_works() {
+        assert_eq!(timestamp_from_duration(Duration::ZERO), "0:00:00.000");
+        assert_eq!(
+            timestamp_from_duration(Duration::from_millis(1_296)),
+            "0:00:01.296"
+        );
+        assert_eq!(
+            timestamp_from_duration(Duration::from_millis(62_050)),
+            "0:01:02.050"
+        );
+        assert_eq!(
+            timestamp_from_duration(Duration::from_secs(3_725)),
+            "1:02:05.000"
+        );
+    }
+
+    #[test]
+    fn cards_start_with_their_slots() {
+        let mut config = Config::default();
+        config.out.leading_silence = 1.5;
+        let voice = |secs| Voice {
+            text: Text::new(TextKind::Comment, "Comment"),
+            bytes: Vec::new(),
+            duration: Duration::from_secs(secs),
+            estimated: true,
+        };
+        let slots = timeline(&config, &[voice(2), voice(3)]);
+
+        // Start of each card is the sum of durations before it
+        let mut start = Duration::ZERO;
+        let mut starts = Vec::new();
+        let list = cards_list(&slots);
+        let mut lines = list.lines().skip(1);
+        while let (Some(file), Some(duration)) = (lines.next(), lines.next()) {
+            starts.push((file.to_string(), start));
+            let seconds: f32 = duration.trim_start_matches("duration ").parse().unwrap();
+            start += Duration::from_secs_f32(seconds);
+        }
+        assert_eq!(
+            starts[1],
+            ("file 'cards/0.png'".to_string(), slots[0].start)
+        );
+        assert_eq!(
+            starts[2],
+            ("file 'cards/1.png'".to_string(), slots[1].start)
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use rand::{rngs::StdRng, Rng};
+use std::time::Duration;
+
+use super::{background::list_files, duration_from_secs, format_seconds, probe};
+use crate::config::{self, Config};
+
+/// File extensions of music tracks, when reading from directory
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "wav", "ogg", "flac", "m4a"];
+
+/// Choose a random music track, if music is enabled
+///
+/// Track is skipped with a warning, if it has no audio stream. Probing is skipped for a dry run
+pub fn choose_music(config: &Config, rng: &mut StdRng, probe_track: bool) -> Option<String> {
+    let paths = list_files(config.assets.music.as_ref()?, AUDIO_EXTENSIONS);
+    if paths.is_empty() {
+        eprintln!("[warning] No music tracks found");
+        return None;
+    }
+    let path = paths[rng.gen_range(0..paths.len())].clone();
+    if !probe_track {
+        return Some(path);
+    }
+
+    match probe(&config.ffmpeg.probe_path, &path) {
+        Ok(info) if info.audio => Some(path),
+        Ok(_) => {
+            eprintln!("[warning] Music track '{path}' has no audio stream");
+            None
+        }
+        Err(err) => {
+            eprintln!("[warning] Failed to probe music track '{path}' - {err}");
+            None
+        }
+    }
+}
+
+/// Filter to mix music input under voice input, outputting `[audio]`
+///
+/// Music fades in and out over the length of the video, and is ducked while speech plays
+pub fn music_filter(config: &config::Music, voice: &str, music: &str, length: Duration) -> String {
+    let config::Music {
+        volume,
+        fade_in,
+        fade_out,
+        ducking,
+        duck_threshold,
+        duck_ratio,
+    } = config;
+
+    let fade_out_start = format_seconds(length.saturating_sub(duration_from_secs(*fade_out)));
+
+    let mut filters = vec![
+        // Pad voice with silence, so music continues over outro
+        format!("[{voice}]apad,asplit=2[voice][sidechain]"),
+        format!(
+            "[{music}]volume={volume},afade=t=in:st=0:d={fade_in},afade=t=out:st={fade_out_start}:d={fade_out}[music]"
+        ),
+    ];
+
+    if *ducking {
+        // Compress music when voice is louder than threshold
+        filters.push(format!(
+            "[music][sidechain]sidechaincompress=threshold={duck_threshold}:ratio={duck_ratio}:attack=20:release=400[ducked]"
+        ));
+    } else {
+        filters.push("[music]anull[ducked]".to_string());
+        filters.push("[sidechain]anullsink".to_string());
+    }
+
+    // Mix without lowering volume of voice
+    filters.push(
+        "[voice][ducked]amix=inputs=2:duration=first:dropout_transition=0:normalize=0[audio]"
+            .to_string(),
+    );
+
+    filters.join(";")
+}
@@ -0,0 +1,450 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fs::{self, OpenOptions},
+    io::ErrorKind,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+};
+
+use crate::{config::Config, Text};
+
+/// Filename of manifest, in output directory
+const MANIFEST_NAME: &str = "manifest.json";
+
+/// How to handle an output file which already exists
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Collision {
+    /// Replace existing file
+    #[default]
+    Overwrite,
+    /// Add number to filename, such as `video-1.mp4`
+    Rename,
+    /// Keep existing file, and skip rendering
+    Skip,
+    /// Stop with error
+    Error,
+}
+
+/// Paths of outputs reserved by jobs of this process, which are not finished yet
+static IN_USE: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+fn in_use() -> MutexGuard<'static, BTreeSet<String>> {
+    IN_USE.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// Output file of one video, or one part of video
+#[derive(Debug, Clone)]
+pub struct Output {
+    /// Path to video file, including output directory
+    pub path: String,
+    /// Number of part, if video was split
+    pub part: Option<usize>,
+    /// Shared by copies of output, and cleaned up when last copy is dropped
+    claim: Arc<Claim>,
+}
+
+/// Claim of output file, until it is finished
+///
+/// If file is not finished, its partial file and the empty file created to reserve it are removed
+/// on drop, such as after a failed render.
+#[derive(Debug)]
+struct Claim {
+    path: String,
+    /// Whether path is listed as in use by this process
+    reserved: bool,
+    /// Whether empty file was created to reserve path
+    created: bool,
+    finished: AtomicBool,
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        if !*self.finished.get_mut() {
+            let _ = fs::remove_file(partial_path(&self.path));
+            if self.created {
+                let _ = fs::remove_file(&self.path);
+            }
+        }
+        if self.reserved {
+            in_use().remove(&self.path);
+        }
+    }
+}
+
+impl Output {
+    /// Output file which is not part of a series, such as a preview
+    pub fn single(path: impl Into<String>) -> Self {
+        Self::claimed(path.into(), false, false)
+    }
+
+    fn claimed(path: String, reserved: bool, created: bool) -> Self {
+        Self {
+            claim: Arc::new(Claim {
+                path: path.clone(),
+                reserved,
+                created,
+                finished: AtomicBool::new(false),
+            }),
+            path,
+            part: None,
+        }
+    }
+
+    /// Path that file is written to, until it is finished
+    ///
+    /// Existing file at final path is only replaced once new file is complete
+    pub fn partial_path(&self) -> String {
+        partial_path(&self.path)
+    }
+
+    /// Move complete file from partial path to final path, so it is kept
+    pub fn finish(&self) -> Result<(), String> {
+        fs::rename(self.partial_path(), &self.path)
+            .map_err(|err| format!("Failed to move output to '{}' - {err}", self.path))?;
+        self.claim.finished.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Path of file saved next to video, with extension of video replaced
+    pub fn sidecar(&self, extension: &str) -> String {
+        match self.path.rsplit_once('.') {
+            // Ignore dots in directory names
+            Some((stem, ext)) if !ext.contains('/') => format!("{stem}.{extension}"),
+            _ => format!("{}.{extension}", self.path),
+        }
+    }
+}
+
+/// Resolve output file of video from filename template, output directory, and collision policy
+///
+/// Fields of template are filled from `post`, the first post of whole video, so all parts share
+/// them. If `reserve` is set, the file is created empty, so jobs running in parallel do not
+/// choose the same file. Returns `None` if file exists and should be skipped.
+pub fn resolve_output(
+    config: &Config,
+    post: Option<&Text>,
+    part: Option<usize>,
+    now: &DateTime<Local>,
+    reserve: bool,
+) -> Result<Option<Output>, String> {
+    let template = match part {
+        Some(_) if !config.out.name.contains("{part}") => &config.out.part_name,
+        _ => &config.out.name,
+    };
+    let name = fill_template(template, post, part, now);
+
+    fs::create_dir_all(&config.out.dir)
+        .map_err(|err| format!("Failed to create output directory - {err}"))?;
+    let path = Path::new(&config.out.dir)
+        .join(name)
+        .to_string_lossy()
+        .to_string();
+
+    let output = resolve_collision(path, config.out.collision, reserve)?;
+    Ok(output.map(|output| Output { part, ..output }))
+}
+
+/// Resolve path of file which may already exist, with collision policy
+///
+/// If `reserve` is set, the file is created empty, so it is not chosen again, and is removed again
+/// if output is dropped before it is finished. File which is being written by another job of this
+/// process is never overwritten, and a new name is chosen instead. Returns `None` if file exists
+/// and should be skipped.
+pub fn resolve_collision(
+    path: String,
+    collision: Collision,
+    reserve: bool,
+) -> Result<Option<Output>, String> {
+    // Held while choosing, so jobs running in parallel do not choose the same file
+    let mut in_use = in_use();
+    let used = in_use.contains(&path);
+    let mut output = |path: String, created: bool| {
+        if reserve {
+            in_use.insert(path.clone());
+        }
+        Some(Output::claimed(path, reserve, created))
+    };
+
+    if claim(&path, reserve)? {
+        return Ok(output(path, reserve));
+    }
+    match collision {
+        Collision::Overwrite if !used => Ok(output(path, false)),
+        Collision::Skip => Ok(None),
+        Collision::Error => Err(format!("Output file '{path}' already exists")),
+        Collision::Overwrite | Collision::Rename => {
+            if collision == Collision::Overwrite {
+                println!("[info] Output file '{path}' is used by another job, renaming");
+            }
+            let (stem, extension) = match path.rsplit_once('.') {
+                Some((stem, extension)) if !extension.contains('/') => {
+                    (stem.to_string(), format!(".{extension}"))
+                }
+                _ => (path.clone(), String::new()),
+            };
+            let mut i = 1;
+            let path = loop {
+                let path = format!("{stem}-{i}{extension}");
+                if claim(&path, reserve)? {
+                    break path;
+                }
+                i += 1;
+            };
+            Ok(output(path, reserve))
+        }
+    }
+}
+
+/// Path that file is written to until it is finished, such as `video.partial.mp4`
+fn partial_path(path: &str) -> String {
+    match path.rsplit_once('.') {
+        Some((stem, ext)) if !ext.contains('/') => format!("{stem}.partial.{ext}"),
+        _ => format!("{path}.partial"),
+    }
+}
+
+/// Create output file if it does not exist, or only check that it does not exist if not
+/// `create`
+///
+/// Returns whether file is free to use
+fn claim(path: &str, create: bool) -> Result<bool, String> {
+    if !create {
+        return Ok(!Path::new(path).exists());
+    }
+    match OpenOptions::new().write(true).create_new(true).open(path) {
+        Ok(_) => Ok(true),
+        Err(err) if err.kind() == ErrorKind::AlreadyExists => Ok(false),
+        Err(err) => Err(format!("Failed to create output file '{path}' - {err}")),
+    }
+}
+
+/// Replace template fields of filename with values from first post of video
+///
+/// Fields are `{subreddit}`, `{post_id}`, `{title}`, `{date}`, `{time}`, and `{part}`.
+/// If video is not split, `{part}` is removed with the separator before it.
+fn fill_template(
+    template: &str,
+    post: Option<&Text>,
+    part: Option<usize>,
+    now: &DateTime<Local>,
+) -> String {
+    let source = post.and_then(|post| post.source.as_ref());
+
+    let mut name = template.to_string();
+    match part {
+        Some(part) => name = name.replace("{part}", &part.to_string()),
+        None => {
+            for separator in ["-", "_", " ", "."] {
+                name = name.replace(&format!("{separator}{{part}}"), "");
+            }
+            name = name.replace("{part}", "");
+        }
+    }
+
+    let fields = [
+        ("{subreddit}", source.map(|source| source.subreddit.clone())),
+        (
+            "{post_id}",
+            source.and_then(|source| source.post_id().map(String::from)),
+        ),
+        ("{title}", post.map(|post| post.content.clone())),
+        ("{date}", Some(now.format("%Y-%m-%d").to_string())),
+        ("{time}", Some(now.format("%H%M%S").to_string())),
+    ];
+    for (field, value) in fields {
+        if name.contains(field) {
+            let value = value.as_deref().map_or("unknown".to_string(), slugify);
+            name = name.replace(field, &value);
+        }
+    }
+    name
+}
+
+/// Make text safe for filename, with only lowercase letters, numbers, and dashes
+fn slugify(text: &str) -> String {
+    /// Maximum length of field in filename
+    const MAX_CHARS: usize = 50;
+
+    let mut slug = String::new();
+    for char in text.chars().flat_map(char::to_lowercase) {
+        if char.is_alphanumeric() {
+            slug.push(char);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug: String = slug.chars().take(MAX_CHARS).collect();
+    slug.trim_end_matches('-').to_string()
+}
+
+/// Files produced by one run, recorded in manifest
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Time of run, in RFC 3339 format
+    pub created: String,
+    pub subreddit: Option<String>,
+    pub post_id: Option<String>,
+    pub title: Option<String>,
+    /// Paths of videos and files saved next to them
+    pub files: Vec<String>,
+}
+
+impl ManifestEntry {
+    /// Create empty entry for run, with first post of video
+    pub fn new(post: Option<&Text>, now: &DateTime<Local>) -> Self {
+        let source = post.and_then(|post| post.source.as_ref());
+        Self {
+            created: now.to_rfc3339(),
+            subreddit: source.map(|source| source.subreddit.clone()),
+            post_id: source.and_then(|source| source.post_id().map(String::from)),
+            title: post.map(|post| post.content.clone()),
+            files: Vec::new(),
+        }
+    }
+
+    /// Record file, if it exists
+    pub fn add_file(&mut self, path: &str) {
+        if Path::new(path).exists() {
+            self.files.push(path.to_string());
+        }
+    }
+}
+
+/// Append entry to manifest in output directory
+///
+/// Manifest is a JSON list of entries, one for each run. Returns path of manifest.
+pub fn save_manifest(config: &Config, entry: ManifestEntry) -> Result<String, String> {
+    // Jobs running in parallel must not overwrite each other's entries
+    static LOCK: Mutex<()> = Mutex::new(());
+    let _guard = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+
+    let path = Path::new(&config.out.dir)
+        .join(MANIFEST_NAME)
+        .to_string_lossy()
+        .to_string();
+
+    let mut entries: Vec<ManifestEntry> = match fs::read_to_string(&path) {
+        Ok(text) => serde_json::from_str(&text)
+            .map_err(|err| format!("Failed to parse manifest '{path}' - {err}"))?,
+        Err(_) => Vec::new(),
+    };
+    entries.push(entry);
+
+    let json = serde_json::to_string_pretty(&entries).expect("Failed to serialize manifest");
+    fs::write(&path, json).map_err(|err| format!("Failed to save manifest - {err}"))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugify_works() {
+        assert_eq!(slugify("AskReddit"), "askreddit");
+        assert_eq!(
+            slugify("What's the best advice you've ever heard?"),
+            "what-s-the-best-advice-you-ve-ever-heard"
+        );
+        assert_eq!(slugify("  --hello--  "), "hello");
+    }
+
+    #[test]
+    fn all_parts_fill_template_from_post() {
+        let post = Text {
+            kind: crate::TextKind::Title,
+            content: "Best advice?".to_string(),
+            source: Some(crate::Source {
+                subreddit: "AskReddit".to_string(),
+                author: "someone".to_string(),
+                score: 1,
+                link: Some("/r/AskReddit/comments/abc123/best_advice/".to_string()),
+                flair: None,
+            }),
+        };
+        let now = Local::now();
+        let template = "{subreddit}-{post_id}-{title}-{part}.mp4";
+        assert_eq!(
+            fill_template(template, Some(&post), Some(2), &now),
+            "askreddit-abc123-best-advice-2.mp4"
+        );
+        assert_eq!(
+            fill_template(template, None, None, &now),
+            "unknown-unknown-unknown.mp4"
+        );
+    }
+
+    #[test]
+    fn reserved_outputs_are_renamed() {
+        let dir = std::env::temp_dir().join(format!("reddit-video-output-{}", std::process::id()));
+        let mut config = Config::default();
+        config.out.dir = dir.to_string_lossy().to_string();
+        config.out.collision = Collision::Rename;
+
+        let now = Local::now();
+        let resolve = || resolve_output(&config, None, None, &now, true).unwrap();
+        let first = resolve().unwrap();
+        let second = resolve().unwrap();
+        assert!(first.path.ends_with("video.mp4"));
+        assert!(second.path.ends_with("video-1.mp4"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn failed_render_removes_reserved_file() {
+        let dir = std::env::temp_dir().join(format!("reddit-video-failed-{}", std::process::id()));
+        let mut config = Config::default();
+        config.out.dir = dir.to_string_lossy().to_string();
+        config.out.collision = Collision::Skip;
+
+        let now = Local::now();
+        let resolve = || resolve_output(&config, None, None, &now, true).unwrap();
+        let output = resolve().unwrap();
+        fs::write(output.partial_path(), "incomplete").unwrap();
+        let (path, partial) = (output.path.clone(), output.partial_path());
+        drop(output);
+        assert!(!Path::new(&path).exists());
+        assert!(!Path::new(&partial).exists());
+
+        // Rerun is not skipped, and finished file is kept
+        let output = resolve().unwrap();
+        fs::write(output.partial_path(), "complete").unwrap();
+        output.finish().unwrap();
+        drop(output);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "complete");
+        assert!(resolve().is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn outputs_in_use_are_not_overwritten() {
+        let dir = std::env::temp_dir().join(format!("reddit-video-in-use-{}", std::process::id()));
+        let mut config = Config::default();
+        config.out.dir = dir.to_string_lossy().to_string();
+        config.out.collision = Collision::Overwrite;
+
+        let now = Local::now();
+        let resolve = || {
+            resolve_output(&config, None, None, &now, true)
+                .unwrap()
+                .unwrap()
+        };
+        let first = resolve();
+        let second = resolve();
+        assert!(first.path.ends_with("video.mp4"));
+        assert!(second.path.ends_with("video-1.mp4"));
+
+        // Finished file is overwritten by later job
+        fs::write(first.partial_path(), "").unwrap();
+        first.finish().unwrap();
+        drop(first);
+        assert!(resolve().path.ends_with("video.mp4"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,172 @@
+use std::{ops::Range, time::Duration};
+
+use super::timeline::{duration_from_secs, leading_silence, pauses};
+use super::{outro_duration, timestamp_from_duration};
+use crate::{config::Config, voice::Voice};
+
+/// Maximum length of video, from config or output profile
+pub fn max_duration(config: &Config) -> Option<Duration> {
+    match config.out.max_duration {
+        Some(seconds) => Some(duration_from_secs(seconds)),
+        None => config.out.profile.max_duration(),
+    }
+}
+
+/// Maximum length of each part, from config or maximum length of video
+fn max_part_duration(config: &Config) -> Option<Duration> {
+    match config.out.part_duration {
+        Some(seconds) => Some(duration_from_secs(seconds)),
+        None => max_duration(config),
+    }
+}
+
+/// Split text frames into consecutive parts, or trim to maximum length if splitting is disabled
+///
+/// Frames are never cut partway through
+pub fn split_parts(config: &Config, voices: Vec<Voice>) -> Vec<Vec<Voice>> {
+    if !config.out.split {
+        return vec![limit_duration(config, voices)];
+    }
+
+    let Some(max_duration) = max_part_duration(config) else {
+        eprintln!("[warning] splitting enabled, but no part duration or profile cap is set");
+        return vec![voices];
+    };
+    // Leading silence and outro are added to every part
+    let outro = outro_duration(config) + leading_silence(config);
+    let max_duration = max_duration.saturating_sub(outro);
+
+    let frames = frame_durations(config, &voices);
+    let trailing = duration_from_secs(config.out.trailing_padding);
+    let ranges = partition(&frames, trailing, max_duration);
+
+    // Move voices into parts, keeping order
+    let mut voices = voices.into_iter();
+    ranges
+        .into_iter()
+        .map(|range| {
+            if range.len() == 1 && frames[range.start].0 + trailing > max_duration {
+                eprintln!(
+                    "[warning] text frame {} is longer than part duration of {}",
+                    range.start,
+                    timestamp_from_duration(max_duration + outro)
+                );
+            }
+            voices.by_ref().take(range.len()).collect()
+        })
+        .collect()
+}
+
+/// Remove text frames which would not fit within maximum length of video
+///
+/// Frames are never cut partway through
+fn limit_duration(config: &Config, mut voices: Vec<Voice>) -> Vec<Voice> {
+    let Some(max_duration) = max_duration(config) else {
+        return voices;
+    };
+    // Leading silence and outro are added to every part
+    let outro = outro_duration(config) + leading_silence(config);
+    let max_duration = max_duration.saturating_sub(outro);
+
+    // Final frame is followed by trailing padding, instead of pause
+    let trailing = duration_from_secs(config.out.trailing_padding);
+    let mut total_duration = Duration::ZERO;
+    let count = frame_durations(config, &voices)
+        .into_iter()
+        .take_while(|(speech, pause)| {
+            let fits = total_duration + *speech + trailing <= max_duration;
+            total_duration += *speech + *pause;
+            fits
+        })
+        .count();
+
+    if count < voices.len() {
+        println!(
+            "  [info] trimmed {} text frames to fit within {}",
+            voices.len() - count,
+            timestamp_from_duration(max_duration + outro)
+        );
+    }
+    voices.truncate(count);
+    voices
+}
+
+/// Duration of speech of each text frame, and of pause after it
+fn frame_durations(config: &Config, voices: &[Voice]) -> Vec<(Duration, Duration)> {
+    voices
+        .iter()
+        .zip(pauses(config, voices))
+        .map(|(voice, pause)| (voice.duration, pause))
+        .collect()
+}
+
+/// Group consecutive frames, as speech and pause durations, into ranges each no longer than
+/// `max`
+///
+/// Final frame of each range is followed by `trailing` padding instead of its pause. A single
+/// frame longer than `max` is given its own range.
+fn partition(
+    frames: &[(Duration, Duration)],
+    trailing: Duration,
+    max: Duration,
+) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    let mut total = Duration::ZERO;
+
+    for (i, (speech, pause)) in frames.iter().enumerate() {
+        // Start new part, if frame would not fit as final frame of current part
+        if i > start && total + *speech + trailing > max {
+            ranges.push(start..i);
+            start = i;
+            total = Duration::ZERO;
+        }
+        total += *speech + *pause;
+    }
+
+    if start < frames.len() {
+        ranges.push(start..frames.len());
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Frames with given speech durations, and no pause
+    fn secs(secs: &[u64]) -> Vec<(Duration, Duration)> {
+        secs.iter()
+            .map(|s| (Duration::from_secs(*s), Duration::ZERO))
+            .collect()
+    }
+
+    #[test]
+    fn partition_works() {
+        let max = Duration::from_secs(10);
+        let none = Duration::ZERO;
+        assert!(partition(&[], none, max).is_empty());
+        assert_eq!(partition(&secs(&[3, 3, 3]), none, max), vec![0..3]);
+        assert_eq!(partition(&secs(&[5, 5, 5]), none, max), vec![0..2, 2..3]);
+        assert_eq!(
+            partition(&secs(&[4, 7, 2, 9]), none, max),
+            vec![0..1, 1..3, 3..4]
+        );
+        assert_eq!(
+            partition(&secs(&[12, 3, 15]), none, max),
+            vec![0..1, 1..2, 2..3]
+        );
+    }
+
+    #[test]
+    fn partition_uses_trailing_padding_for_final_frame() {
+        let max = Duration::from_secs(10);
+        // Pause of 1s between frames, but 3s of padding after final frame of part
+        let frames = vec![(Duration::from_secs(4), Duration::from_secs(1)); 3];
+        let trailing = Duration::from_secs(3);
+        // 4 + 1 + 4 + 3 would be 12s
+        assert_eq!(partition(&frames, trailing, max), vec![0..1, 1..2, 2..3]);
+        // 4 + 1 + 4 + 0 fits
+        assert_eq!(partition(&frames, Duration::ZERO, max), vec![0..2, 2..3]);
+    }
+}
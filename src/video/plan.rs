@@ -0,0 +1,116 @@
+use serde::Serialize;
+use std::fs;
+
+use super::{timeline, Background, Output, Render};
+use crate::{config::Config, voice::Voice};
+
+/// Complete plan of one video, for a dry run without fetching voices or rendering
+#[derive(Debug, Serialize)]
+pub struct Plan<'a> {
+    /// Output filename
+    pub output: String,
+    /// Number of part, if video was split
+    pub part: Option<usize>,
+    /// Length of video, in seconds
+    pub length: f32,
+    pub background: &'a Background,
+    pub music: Option<&'a str>,
+    pub frames: Vec<PlannedFrame<'a>>,
+    /// Full ffmpeg command
+    pub command: String,
+    /// Filter graph script, read by command
+    pub filter_script: String,
+}
+
+/// Text frame with its timing, in seconds
+#[derive(Debug, Serialize)]
+pub struct PlannedFrame<'a> {
+    pub kind: String,
+    pub text: &'a str,
+    pub start: f32,
+    pub speech_end: f32,
+    pub end: f32,
+    /// Duration of speech is estimated from text length
+    pub estimated: bool,
+}
+
+/// Create plan of video, from its voices and render command
+pub fn plan<'a>(
+    config: &Config,
+    voices: &'a [Voice],
+    background: &'a Background,
+    music: Option<&'a str>,
+    render: &Render,
+    output: &Output,
+) -> Plan<'a> {
+    let frames = voices
+        .iter()
+        .zip(timeline(config, voices))
+        .map(|(voice, slot)| PlannedFrame {
+            kind: format!("{:?}", voice.text.kind),
+            text: &voice.text.content,
+            start: slot.start.as_secs_f32(),
+            speech_end: slot.speech_end.as_secs_f32(),
+            end: slot.end.as_secs_f32(),
+            estimated: voice.estimated,
+        })
+        .collect();
+
+    Plan {
+        output: output.path.clone(),
+        part: output.part,
+        length: render.length.as_secs_f32(),
+        background,
+        music,
+        frames,
+        command: render.command_line(),
+        filter_script: render.script.clone(),
+    }
+}
+
+impl Plan<'_> {
+    /// Print plan to stdout
+    pub fn print(&self) {
+        println!("Output: {}", self.output);
+        println!("Background: {:#?}", self.background);
+        if let Some(music) = self.music {
+            println!("Music: {music}");
+        }
+
+        println!("Frames:");
+        for (i, frame) in self.frames.iter().enumerate() {
+            println!(
+                "  {i:>3}  {:>8.3} - {:>8.3}  (speech {:.3}{})  {:<7}  {}",
+                frame.start,
+                frame.end,
+                frame.speech_end - frame.start,
+                if frame.estimated { ", estimated" } else { "" },
+                frame.kind,
+                preview(frame.text),
+            );
+        }
+        println!("Length: {:.3}s", self.length);
+
+        println!("Command:\n{}", self.command);
+        println!("Filter script:\n{}", self.filter_script);
+    }
+
+    /// Save plan as JSON next to output file
+    pub fn save(&self, output: &Output) {
+        let path = output.sidecar("plan.json");
+        let json = serde_json::to_string_pretty(self).expect("Failed to serialize plan");
+        fs::write(&path, json).expect("Failed to save plan");
+        println!("Saved plan to {path}");
+    }
+}
+
+/// First line of text, shortened to fit on one line
+fn preview(text: &str) -> String {
+    const MAX_CHARS: usize = 50;
+    let line = text.lines().next().unwrap_or_default();
+    if line.chars().count() > MAX_CHARS {
+        format!("{}...", line.chars().take(MAX_CHARS).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
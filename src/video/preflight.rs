@@ -0,0 +1,207 @@
+use std::{process, time::Duration};
+
+use super::{build_render, graph::filter_names, Background, Encoder, Output, VideoStream};
+use crate::{config::Config, voice::Voice, Source, Text, TextKind};
+
+/// Oldest major version of ffmpeg known to support all options used
+const MIN_MAJOR_VERSION: u32 = 4;
+
+/// Versions of ffmpeg tools found by preflight
+#[derive(Debug)]
+pub struct Tools {
+    pub ffmpeg_version: String,
+    pub ffprobe_version: String,
+}
+
+/// Check that ffmpeg and ffprobe can be run, and support all filters and encoders used by config
+///
+/// This should run before fetching content or creating voices, so a missing tool fails early.
+/// Returns every missing piece in the error, one per line.
+pub fn preflight(config: &Config) -> Result<Tools, String> {
+    let paths = &config.ffmpeg;
+    let mut problems = Vec::new();
+
+    let ffmpeg_version = match tool_output(&paths.path, &["-version"]) {
+        Ok(output) => parse_version(&output),
+        Err(err) => return Err(format!("ffmpeg not found at '{}' - {err}", paths.path)),
+    };
+    let ffprobe_version = match tool_output(&paths.probe_path, &["-version"]) {
+        Ok(output) => parse_version(&output),
+        Err(err) => {
+            problems.push(format!(
+                "ffprobe not found at '{}' - {err}",
+                paths.probe_path
+            ));
+            String::new()
+        }
+    };
+
+    if let Some(major) = major_version(&ffmpeg_version) {
+        if major < MIN_MAJOR_VERSION {
+            problems.push(format!(
+                "ffmpeg version {ffmpeg_version} is too old, version {MIN_MAJOR_VERSION} or newer is required"
+            ));
+        }
+    }
+
+    let filters = tool_output(&paths.path, &["-filters"])
+        .map(|output| list_names(&output))
+        .map_err(|err| format!("Failed to list filters of ffmpeg - {err}"))?;
+    for filter in required_filters(config) {
+        if !filters.contains(&filter) {
+            problems.push(format!("ffmpeg is missing filter '{filter}'"));
+        }
+    }
+
+    let encoders = tool_output(&paths.path, &["-encoders"])
+        .map(|output| list_names(&output))
+        .map_err(|err| format!("Failed to list encoders of ffmpeg - {err}"))?;
+    let mut required_encoders = Vec::new();
+    // Preview is encoded with its own fast settings
+    for encoder in [
+        Encoder::from_config(&config.out),
+        Encoder::preview(&config.preview),
+    ] {
+        for name in [encoder.video_codec.encoder(), encoder.audio_codec.as_str()] {
+            if !required_encoders.contains(&name.to_string()) {
+                required_encoders.push(name.to_string());
+            }
+        }
+    }
+    for name in required_encoders {
+        if !encoders.contains(&name) {
+            problems.push(format!("ffmpeg is missing encoder '{name}'"));
+        }
+    }
+
+    if !problems.is_empty() {
+        return Err(problems.join("\n"));
+    }
+    Ok(Tools {
+        ffmpeg_version,
+        ffprobe_version,
+    })
+}
+
+/// Filters used to render video with config
+///
+/// Filters are read from filter graphs of a sample video and preview, built from texts of every
+/// kind, so the list matches what rendering emits
+fn required_filters(config: &Config) -> Vec<String> {
+    let source = Source {
+        subreddit: "askreddit".to_string(),
+        author: "user".to_string(),
+        score: 1,
+        link: None,
+        flair: None,
+    };
+    let voice = |kind, audible: bool| Voice {
+        text: Text {
+            kind,
+            content: "Text".to_string(),
+            source: Some(source.clone()),
+        },
+        // Voice without audio is rendered as generated silence
+        bytes: if audible { vec![0] } else { Vec::new() },
+        duration: Duration::from_secs(1),
+        estimated: false,
+    };
+    let mut voices = vec![
+        voice(TextKind::Title, true),
+        voice(TextKind::Body, false),
+        voice(TextKind::Comment, true),
+    ];
+    if config.intro.enabled {
+        voices.insert(0, voice(TextKind::Intro, false));
+    }
+
+    let background = Background {
+        path: "background.mp4".to_string(),
+        offset: 0.0,
+        looped: true,
+        still: false,
+        seed: 0,
+        video: VideoStream {
+            index: 0,
+            width: 1920,
+            height: 1080,
+            frame_rate: None,
+        },
+    };
+    let music = config.assets.music.as_ref().map(|_| "music.mp3");
+    let mut output = Output::single("preflight.mp4");
+    output.part = config.out.split.then_some(1);
+
+    let mut filters = Vec::new();
+    for preview in [None, Some(&config.preview)] {
+        let render = build_render(config, ".", &voices, &background, music, &output, preview);
+        filters.extend(filter_names(&render.script));
+    }
+    // Frame of thumbnail is extracted by its own command
+    if config.thumbnail.enabled {
+        filters.extend(["scale".to_string(), "crop".to_string()]);
+    }
+
+    let mut unique = Vec::new();
+    for filter in filters {
+        if !unique.contains(&filter) {
+            unique.push(filter);
+        }
+    }
+    unique
+}
+
+/// Run tool with arguments, returning its standard output
+fn tool_output(path: &str, args: &[&str]) -> Result<String, String> {
+    let output = process::Command::new(path)
+        .arg("-hide_banner")
+        .args(args)
+        .output()
+        .map_err(|err| err.to_string())?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Get version from first line of `-version` output, such as `ffmpeg version 6.1.1 Copyright...`
+fn parse_version(output: &str) -> String {
+    output
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(2))
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+/// Get major version number, if not a development build (such as `N-112345-g...`)
+fn major_version(version: &str) -> Option<u32> {
+    let version = version.strip_prefix('n').unwrap_or(version);
+    let digits: String = version.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+/// Get names of items in `-filters` or `-encoders` output
+///
+/// Each item is listed as flags, then name, then description
+fn list_names(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn major_version_works() {
+        assert_eq!(major_version("6.1.1-3ubuntu5"), Some(6));
+        assert_eq!(major_version("n7.0"), Some(7));
+        assert_eq!(major_version("4.4.2"), Some(4));
+        assert_eq!(major_version("N-112345-g0123abcd"), None);
+    }
+}
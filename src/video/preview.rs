@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use super::{
+    build_render, duration_from_secs, ffmpeg::FFMpegCommand, format_seconds, outro_duration,
+    resolve_collision, timeline, total_duration, Background, FFMpegError, Output, Progress,
+};
+use crate::{
+    config::{self, Config},
+    voice::Voice,
+};
+
+/// Render fast, low-resolution preview of first part of video
+///
+/// Only first frames are rendered if `frames` is set, then only time window if set
+pub fn render_preview(
+    config: &Config,
+    dir: &str,
+    voices: &[Voice],
+    background: &Background,
+    music: Option<&str>,
+    output: &Output,
+    on_progress: impl FnMut(&Progress),
+) -> Result<(), FFMpegError> {
+    let voices = preview_voices(&config.preview, voices);
+    let render = build_render(
+        config,
+        dir,
+        voices,
+        background,
+        music,
+        output,
+        Some(&config.preview),
+    );
+    render.show_command();
+    render.run(config, voices, on_progress)
+}
+
+/// Save still image of each chosen text frame from rendered preview, as PNG next to preview
+///
+/// Image is taken from middle of speech of frame. Frames outside of preview are skipped.
+/// Existing images are handled with collision policy of outputs, unlike preview itself.
+pub fn save_thumbnails(config: &Config, voices: &[Voice]) -> Result<(), String> {
+    let preview = &config.preview;
+    let voices = preview_voices(preview, voices);
+    let slots = timeline(config, voices);
+    let total = total_duration(config, voices);
+    let (start, length) = preview_window(preview, total, total + outro_duration(config));
+
+    let output = Output::single(&preview.name);
+    for &i in &preview.thumbnails {
+        let Some(slot) = slots.get(i) else {
+            eprintln!("[warning] Thumbnail frame {i} does not exist");
+            continue;
+        };
+        let time = slot.start + (slot.speech_end - slot.start) / 2;
+        if time < start || time >= start + length {
+            eprintln!("[warning] Thumbnail frame {i} is outside of preview");
+            continue;
+        }
+
+        let path = output.sidecar(&format!("frame{i}.png"));
+        let Some(image) = resolve_collision(path, config.out.collision, true)? else {
+            println!("[info] Image of frame {i} already exists, skipping");
+            continue;
+        };
+
+        // Partial file may be left by interrupted run
+        let mut ffmpeg = FFMpegCommand::new(&config.ffmpeg.path, true);
+        ffmpeg.args(["-ss", &format_seconds(time - start), "-i", &preview.name]);
+        ffmpeg.args(["-frames:v", "1"]);
+        ffmpeg.arg(image.partial_path());
+        ffmpeg
+            .run(None, |_| ())
+            .map_err(|err| err.to_string())
+            .and_then(|()| image.finish())
+            .map_err(|err| format!("Failed to save image of frame {i} - {err}"))?;
+    }
+    Ok(())
+}
+
+/// Start and length of time to render in preview
+///
+/// Preview ends after last text frame if frame count is limited, otherwise after outro
+pub(super) fn preview_window(
+    preview: &config::Preview,
+    frames_end: Duration,
+    outro_end: Duration,
+) -> (Duration, Duration) {
+    let end = if preview.frames.is_some() {
+        frames_end
+    } else {
+        outro_end
+    };
+    let start = preview
+        .start
+        .map_or(Duration::ZERO, duration_from_secs)
+        .min(end);
+    let length = preview
+        .length
+        .map_or(end - start, duration_from_secs)
+        .min(end - start);
+    (start, length)
+}
+
+/// First text frames to render in preview
+fn preview_voices<'a>(preview: &config::Preview, voices: &'a [Voice]) -> &'a [Voice] {
+    match preview.frames {
+        Some(frames) => &voices[..frames.min(voices.len())],
+        None => voices,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preview_window_works() {
+        let frames_end = Duration::from_secs(20);
+        let outro_end = Duration::from_secs(22);
+
+        let preview = config::Preview::default();
+        assert_eq!(
+            preview_window(&preview, frames_end, outro_end),
+            (Duration::ZERO, outro_end)
+        );
+
+        let preview = config::Preview {
+            frames: Some(3),
+            start: Some(5.0),
+            length: Some(30.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            preview_window(&preview, frames_end, outro_end),
+            (Duration::from_secs(5), Duration::from_secs(15))
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use serde::Deserialize;
+use std::{process, time::Duration};
+
+/// Streams and duration of media file, read by ffprobe
+#[derive(Debug, Clone, Default)]
+pub struct MediaInfo {
+    /// Duration of file, if known (such as not for still images)
+    pub duration: Option<Duration>,
+    /// First video stream, excluding cover art
+    pub video: Option<VideoStream>,
+    /// Whether file has an audio stream
+    pub audio: bool,
+}
+
+/// Properties of video stream
+#[derive(Debug, Clone, Copy)]
+pub struct VideoStream {
+    /// Index of stream in file, for stream specifiers such as `0:1`
+    pub index: usize,
+    pub width: u32,
+    pub height: u32,
+    /// Frames per second, if known
+    pub frame_rate: Option<f32>,
+}
+
+/// Output of `ffprobe -print_format json -show_format -show_streams`
+#[derive(Deserialize)]
+struct ProbeJson {
+    #[serde(default)]
+    streams: Vec<StreamJson>,
+    format: Option<FormatJson>,
+}
+
+#[derive(Deserialize)]
+struct StreamJson {
+    index: usize,
+    codec_type: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    avg_frame_rate: Option<String>,
+    r_frame_rate: Option<String>,
+    duration: Option<String>,
+    #[serde(default)]
+    disposition: DispositionJson,
+}
+
+#[derive(Default, Deserialize)]
+struct DispositionJson {
+    #[serde(default)]
+    attached_pic: u8,
+}
+
+#[derive(Deserialize)]
+struct FormatJson {
+    duration: Option<String>,
+}
+
+/// Read streams and duration of media file with ffprobe
+pub fn probe(ffprobe: &str, path: &str) -> Result<MediaInfo, String> {
+    let output = process::Command::new(ffprobe)
+        .args(["-v", "error", "-print_format", "json"])
+        .args(["-show_format", "-show_streams", path])
+        .output()
+        .map_err(|err| format!("{err:?}"))?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    parse_probe(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse JSON output of ffprobe
+fn parse_probe(json: &str) -> Result<MediaInfo, String> {
+    let probe: ProbeJson = serde_json::from_str(json).map_err(|err| format!("{err:?}"))?;
+
+    let video = probe.streams.iter().find(|stream| {
+        stream.codec_type.as_deref() == Some("video") && stream.disposition.attached_pic == 0
+    });
+    let audio = probe
+        .streams
+        .iter()
+        .any(|stream| stream.codec_type.as_deref() == Some("audio"));
+
+    let duration = probe
+        .format
+        .and_then(|format| format.duration)
+        .or_else(|| video.and_then(|stream| stream.duration.clone()))
+        .and_then(|duration| duration.parse::<f32>().ok())
+        .filter(|seconds| seconds.is_finite() && *seconds > 0.0)
+        .map(Duration::from_secs_f32);
+
+    let video = video.and_then(|stream| {
+        Some(VideoStream {
+            index: stream.index,
+            width: stream.width?,
+            height: stream.height?,
+            frame_rate: stream
+                .avg_frame_rate
+                .as_deref()
+                .and_then(parse_frame_rate)
+                .or_else(|| stream.r_frame_rate.as_deref().and_then(parse_frame_rate)),
+        })
+    });
+
+    Ok(MediaInfo {
+        duration,
+        video,
+        audio,
+    })
+}
+
+/// Parse frame rate fraction, such as `30000/1001`
+fn parse_frame_rate(rate: &str) -> Option<f32> {
+    let (numerator, denominator) = rate.split_once('/')?;
+    let numerator: f32 = numerator.parse().ok()?;
+    let denominator: f32 = denominator.parse().ok()?;
+    // Unknown rate is given as `0/0`
+    if numerator <= 0.0 || denominator <= 0.0 {
+        return None;
+    }
+    Some(numerator / denominator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_probe_works() {
+        let json = r#"{
+            "streams": [
+                { "index": 0, "codec_type": "audio", "duration": "61.2" },
+                { "index": 1, "codec_type": "video", "width": 1280, "height": 720,
+                  "avg_frame_rate": "30000/1001", "r_frame_rate": "30/1",
+                  "disposition": { "attached_pic": 0 } }
+            ],
+            "format": { "duration": "61.500000" }
+        }"#;
+        let info = parse_probe(json).unwrap();
+        assert_eq!(info.duration, Some(Duration::from_secs_f32(61.5)));
+        assert!(info.audio);
+        let video = info.video.unwrap();
+        assert_eq!((video.index, video.width, video.height), (1, 1280, 720));
+        assert!((video.frame_rate.unwrap() - 29.97).abs() < 0.01);
+
+        // Cover art is not a video stream
+        let json = r#"{
+            "streams": [
+                { "index": 0, "codec_type": "audio" },
+                { "index": 1, "codec_type": "video", "width": 500, "height": 500,
+                  "avg_frame_rate": "0/0", "disposition": { "attached_pic": 1 } }
+            ],
+            "format": { "duration": "N/A" }
+        }"#;
+        let info = parse_probe(json).unwrap();
+        assert!(info.video.is_none());
+        assert_eq!(info.duration, None);
+    }
+}
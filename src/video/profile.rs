@@ -0,0 +1,138 @@
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Output profile, determining resolution, text layout, and duration cap
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    /// Keep resolution of background video
+    #[default]
+    Original,
+    /// 16:9, for regular Youtube videos
+    Landscape,
+    /// 9:16, for Youtube Shorts, TikTok, and Instagram Reels
+    Vertical,
+    /// 1:1
+    Square,
+}
+
+/// How to fit background into resolution of profile
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Fit {
+    /// Scale to fill canvas, and crop overflowing edges
+    #[default]
+    Crop,
+    /// Scale to fit inside canvas, and pad empty edges with black
+    Pad,
+}
+
+/// Position and size of text on canvas
+#[derive(Debug)]
+pub struct Layout {
+    /// Font size of text frames
+    pub fontsize: u32,
+    /// Maximum characters per line
+    pub wrap_width: usize,
+    /// Horizontal position of text frames (ffmpeg expression)
+    pub x: String,
+    /// Vertical position of text frames (ffmpeg expression)
+    pub y: String,
+    /// Vertical position of card images (ffmpeg overlay expression)
+    pub overlay_y: String,
+    /// Vertical position of title cards (ffmpeg expression)
+    pub title_y: String,
+    /// Horizontal position of watermark (ffmpeg expression)
+    pub watermark_x: String,
+    /// Vertical position of watermark (ffmpeg expression)
+    pub watermark_y: String,
+}
+
+impl Profile {
+    /// Width and height of output video, or `None` to keep background resolution
+    pub fn resolution(self) -> Option<(u32, u32)> {
+        match self {
+            Self::Original => None,
+            Self::Landscape => Some((1920, 1080)),
+            Self::Vertical => Some((1080, 1920)),
+            Self::Square => Some((1080, 1080)),
+        }
+    }
+
+    /// Maximum length of video allowed by platform
+    pub fn max_duration(self) -> Option<Duration> {
+        match self {
+            Self::Vertical => Some(Duration::from_secs(60)),
+            _ => None,
+        }
+    }
+
+    /// Text layout, keeping text inside safe area of platform
+    pub fn layout(self) -> Layout {
+        match self {
+            Self::Original => Layout {
+                fontsize: 32,
+                wrap_width: 60,
+                x: "(w-text_w)/2".to_string(),
+                y: "(h-text_h)/2".to_string(),
+                overlay_y: "(H-h)/2".to_string(),
+                title_y: "h*0.1".to_string(),
+                watermark_x: "w*0.8-text_w/2".to_string(),
+                watermark_y: "h*0.3-text_h/2".to_string(),
+            },
+            Self::Landscape => Layout {
+                fontsize: 48,
+                wrap_width: 60,
+                ..Self::Original.layout()
+            },
+            // Avoid top 15% (channel name) and bottom 25% (caption, buttons) of canvas
+            Self::Vertical => Layout {
+                fontsize: 56,
+                wrap_width: 28,
+                x: "(w-text_w)/2".to_string(),
+                y: "h*0.45-text_h/2".to_string(),
+                overlay_y: "H*0.45-h/2".to_string(),
+                title_y: "h*0.18".to_string(),
+                watermark_x: "w*0.75-text_w/2".to_string(),
+                watermark_y: "h*0.2-text_h/2".to_string(),
+            },
+            Self::Square => Layout {
+                fontsize: 44,
+                wrap_width: 40,
+                ..Self::Original.layout()
+            },
+        }
+    }
+
+    /// Text layout for background of given resolution
+    ///
+    /// Text of original profile is scaled to background, relative to landscape layout
+    pub fn layout_for(self, width: u32, height: u32) -> Layout {
+        if self != Self::Original {
+            return self.layout();
+        }
+        let landscape = Self::Landscape.layout();
+        Layout {
+            fontsize: (landscape.fontsize * width.min(height) / 1080).max(12),
+            wrap_width: if height > width {
+                Self::Vertical.layout().wrap_width
+            } else {
+                landscape.wrap_width
+            },
+            ..self.layout()
+        }
+    }
+
+    /// Filter to scale background to resolution of profile, if not original
+    pub fn scale_filter(self, fit: Fit) -> Option<String> {
+        let (width, height) = self.resolution()?;
+        Some(match fit {
+            Fit::Crop => format!(
+                "scale={width}:{height}:force_original_aspect_ratio=increase,crop={width}:{height},setsar=1"
+            ),
+            Fit::Pad => format!(
+                "scale={width}:{height}:force_original_aspect_ratio=decrease,pad={width}:{height}:(ow-iw)/2:(oh-ih)/2:black,setsar=1"
+            ),
+        })
+    }
+}
@@ -0,0 +1,132 @@
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+
+use super::timestamp_from_duration;
+
+/// Width of progress bar, in characters
+const BAR_WIDTH: usize = 30;
+
+/// Progress of running ffmpeg command
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    /// Time of output rendered so far
+    pub time: Duration,
+    /// Total time of output, if known
+    pub total: Option<Duration>,
+    /// Render speed, relative to playback speed
+    pub speed: Option<f32>,
+    /// Real time since command started
+    pub elapsed: Duration,
+    /// Whether command has finished rendering
+    pub done: bool,
+}
+
+impl Progress {
+    /// Fraction of output rendered (0 to 1), if total is known
+    pub fn fraction(&self) -> Option<f32> {
+        let total = self.total?.as_secs_f32();
+        if total <= 0.0 {
+            return None;
+        }
+        Some((self.time.as_secs_f32() / total).clamp(0.0, 1.0))
+    }
+
+    /// Estimated real time until command finishes, if total is known
+    pub fn eta(&self) -> Option<Duration> {
+        let fraction = self.fraction()?;
+        if fraction <= 0.0 {
+            return None;
+        }
+        Some(self.elapsed.mul_f32((1.0 - fraction) / fraction))
+    }
+
+    /// Update progress with a `key=value` line from `-progress` output
+    ///
+    /// Returns `true` if line ends a progress block
+    pub fn parse_line(&mut self, line: &str) -> bool {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            return false;
+        };
+        match key {
+            // Both are in microseconds
+            "out_time_us" | "out_time_ms" => {
+                if let Ok(micros) = value.parse::<u64>() {
+                    self.time = Duration::from_micros(micros);
+                }
+            }
+            "speed" => {
+                self.speed = value.trim_end_matches('x').trim().parse().ok();
+            }
+            "progress" => {
+                self.done = value == "end";
+                return true;
+            }
+            _ => (),
+        }
+        false
+    }
+}
+
+/// Print progress bar with ETA, overwriting previous line
+pub fn print_progress(progress: &Progress) {
+    let bar = match progress.fraction() {
+        Some(fraction) => {
+            let filled = (fraction * BAR_WIDTH as f32).round() as usize;
+            format!(
+                "[{}{}] {:>3.0}%",
+                "#".repeat(filled),
+                "-".repeat(BAR_WIDTH - filled),
+                fraction * 100.0
+            )
+        }
+        None => format!("[{}]", "?".repeat(BAR_WIDTH)),
+    };
+    let speed = progress
+        .speed
+        .map_or(String::new(), |speed| format!(" {speed:.2}x"));
+    let eta = match progress.eta() {
+        Some(eta) if !progress.done => format!(" ETA {}", timestamp_from_duration(eta)),
+        _ => String::new(),
+    };
+
+    print!(
+        "\r{bar} {}{speed}{eta}\x1b[K",
+        timestamp_from_duration(progress.time)
+    );
+    if progress.done {
+        println!();
+    }
+    io::stdout().flush().ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_line_works() {
+        let mut progress = Progress {
+            total: Some(Duration::from_secs(10)),
+            elapsed: Duration::from_secs(2),
+            ..Default::default()
+        };
+
+        assert!(!progress.parse_line("frame=120"));
+        assert!(!progress.parse_line("out_time_us=2500000"));
+        assert!(!progress.parse_line("speed=1.25x"));
+        assert!(progress.parse_line("progress=continue"));
+
+        assert_eq!(progress.time, Duration::from_millis(2500));
+        assert_eq!(progress.speed, Some(1.25));
+        assert_eq!(progress.fraction(), Some(0.25));
+        assert_eq!(progress.eta(), Some(Duration::from_secs(6)));
+        assert!(!progress.done);
+
+        assert!(!progress.parse_line("speed=N/A"));
+        assert!(progress.parse_line("progress=end"));
+        assert_eq!(progress.speed, None);
+        assert!(progress.done);
+    }
+}
@@ -0,0 +1,112 @@
+use image::imageops;
+
+use super::{
+    duration_from_secs, ffmpeg::FFMpegCommand, format_seconds, resolve_collision, total_duration,
+    Background, Output,
+};
+use crate::{
+    card::{self, THUMBNAIL_HEIGHT, THUMBNAIL_WIDTH},
+    config::{Config, ImageFormat},
+    voice::Voice,
+    Text, TextKind,
+};
+
+/// Create thumbnail of video, from background frame and title of post, saved next to video
+///
+/// Existing thumbnail is handled with collision policy, like video. Returns path of thumbnail,
+/// or `None` if existing thumbnail was kept.
+pub fn create_thumbnail(
+    config: &Config,
+    dir: &str,
+    voices: &[Voice],
+    background: &Background,
+    output: &Output,
+) -> Result<Option<String>, String> {
+    let Some(title) = voices
+        .iter()
+        .map(|voice| &voice.text)
+        .find(|text| matches!(text.kind, TextKind::Title | TextKind::Intro))
+    else {
+        return Err("No post title to show in thumbnail".to_string());
+    };
+
+    let path = output.sidecar(&format!(
+        "thumbnail.{}",
+        config.thumbnail.format.extension()
+    ));
+    let Some(thumbnail) = resolve_collision(path, config.out.collision, true)? else {
+        return Ok(None);
+    };
+
+    // Existing thumbnail is only replaced once new one is saved
+    save_thumbnail(
+        config,
+        dir,
+        voices,
+        background,
+        title,
+        &thumbnail.partial_path(),
+    )?;
+    thumbnail.finish()?;
+    Ok(Some(thumbnail.path))
+}
+
+/// Render thumbnail and save it to `path`
+fn save_thumbnail(
+    config: &Config,
+    dir: &str,
+    voices: &[Voice],
+    background: &Background,
+    title: &Text,
+    path: &str,
+) -> Result<(), String> {
+    // Frame from middle of background section shown in video
+    let time = duration_from_secs(background.offset) + total_duration(config, voices) / 2;
+    let frame_path = format!("{dir}/thumbnail-background.png");
+
+    // Frame is a temporary file of job, which may be left by interrupted run
+    let mut ffmpeg = FFMpegCommand::new(&config.ffmpeg.path, true);
+    ffmpeg.args(background.loop_args());
+    ffmpeg.args(["-ss", &format_seconds(time), "-i", &background.path]);
+    ffmpeg.args(["-map", &format!("0:{}", background.video.index)]);
+    ffmpeg.args(["-frames:v", "1", "-vf"]);
+    // Fill thumbnail, cropping edges
+    ffmpeg.arg(format!(
+        "scale={THUMBNAIL_WIDTH}:{THUMBNAIL_HEIGHT}:force_original_aspect_ratio=increase,crop={THUMBNAIL_WIDTH}:{THUMBNAIL_HEIGHT}"
+    ));
+    ffmpeg.arg(&frame_path);
+    ffmpeg
+        .run(None, |_| ())
+        .map_err(|err| format!("Failed to extract background frame - {err}"))?;
+
+    let frame = image::open(&frame_path)
+        .map_err(|err| format!("Failed to read background frame - {err}"))?
+        .to_rgba8();
+    // Background may be smaller than thumbnail, if scaling failed
+    let frame = imageops::resize(
+        &frame,
+        THUMBNAIL_WIDTH,
+        THUMBNAIL_HEIGHT,
+        imageops::FilterType::Triangle,
+    );
+
+    let font_path = config.thumbnail.font.as_ref().unwrap_or(&config.cards.font);
+    let font = card::load_font(font_path)?;
+    let image = card::render_thumbnail(
+        &font,
+        frame,
+        &title.content,
+        title
+            .source
+            .as_ref()
+            .map(|source| source.subreddit.as_str()),
+        config.assets.watermark.as_deref(),
+    );
+
+    let result = match config.thumbnail.format {
+        ImageFormat::Png => image.save(path),
+        // JPEG has no alpha channel
+        ImageFormat::Jpeg => image::DynamicImage::ImageRgba8(image).to_rgb8().save(path),
+    };
+    result.map_err(|err| format!("Failed to save thumbnail - {err}"))
+}
@@ -0,0 +1,129 @@
+use std::time::Duration;
+
+use crate::{config::Config, voice::Voice, TextKind};
+
+/// Time span of one text frame in video
+#[derive(Debug, Clone, Copy)]
+pub struct Slot {
+    /// Time when voice starts
+    pub start: Duration,
+    /// Time when voice ends, and pause starts
+    pub speech_end: Duration,
+    /// Time when pause ends, and next frame starts
+    pub end: Duration,
+}
+
+/// Convert seconds from config to duration, rounded to nearest millisecond
+pub fn duration_from_secs(seconds: f32) -> Duration {
+    Duration::from_millis((seconds.max(0.0) * 1000.0).round() as u64)
+}
+
+/// Format duration as seconds, with millisecond precision, for ffmpeg arguments
+pub fn format_seconds(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    format!("{}.{:03}", millis / 1000, millis % 1000)
+}
+
+/// Silence before first text frame
+pub fn leading_silence(config: &Config) -> Duration {
+    duration_from_secs(config.out.leading_silence)
+}
+
+/// Length of pause after each text frame
+///
+/// The final frame is followed by the trailing padding, before the outro
+pub fn pauses(config: &Config, voices: &[Voice]) -> Vec<Duration> {
+    (0..voices.len())
+        .map(|i| {
+            let Some(next) = voices.get(i + 1) else {
+                return duration_from_secs(config.out.trailing_padding);
+            };
+            pause_between(config, voices[i].text.kind, next.text.kind)
+        })
+        .collect()
+}
+
+/// Length of pause between two consecutive text frames
+fn pause_between(config: &Config, current: TextKind, next: TextKind) -> Duration {
+    let seconds = match (current, next) {
+        // Posts without body, in posts mode
+        (TextKind::Title, TextKind::Title) => config.pause.between_posts,
+        (TextKind::Title | TextKind::Intro, _) => config.pause.after_title,
+        (_, TextKind::Title) => config.pause.between_posts,
+        _ => config.pause.between_comments,
+    };
+    duration_from_secs(seconds)
+}
+
+/// Whether transition sound should play in pause after text frame
+pub fn has_transition(config: &Config, voices: &[Voice], index: usize) -> bool {
+    if config.pause.transition.is_none() {
+        return false;
+    }
+    let Some(next) = voices.get(index + 1) else {
+        return false;
+    };
+    next.text.kind == TextKind::Comment
+        && !pause_between(config, voices[index].text.kind, next.text.kind).is_zero()
+}
+
+/// Time span of each text frame, including pauses
+pub fn timeline(config: &Config, voices: &[Voice]) -> Vec<Slot> {
+    let mut time = leading_silence(config);
+    voices
+        .iter()
+        .zip(pauses(config, voices))
+        .map(|(voice, pause)| {
+            let start = time;
+            let speech_end = start + voice.duration;
+            time = speech_end + pause;
+            Slot {
+                start,
+                speech_end,
+                end: time,
+            }
+        })
+        .collect()
+}
+
+/// Total duration of text frames, including leading silence and pauses
+pub fn total_duration(config: &Config, voices: &[Voice]) -> Duration {
+    timeline(config, voices)
+        .last()
+        .map_or(leading_silence(config), |slot| slot.end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_seconds_works() {
+        assert_eq!(format_seconds(Duration::ZERO), "0.000");
+        assert_eq!(format_seconds(Duration::from_millis(1_296)), "1.296");
+        assert_eq!(format_seconds(Duration::from_micros(2_500_900)), "2.500");
+        assert_eq!(format_seconds(Duration::from_secs(61)), "61.000");
+    }
+
+    #[test]
+    fn duration_from_secs_works() {
+        assert_eq!(duration_from_secs(0.8), Duration::from_millis(800));
+        assert_eq!(duration_from_secs(1.2345), Duration::from_millis(1_235));
+        assert_eq!(duration_from_secs(-1.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn pause_between_works() {
+        let mut config = Config::default();
+        config.pause.after_title = 1.0;
+        config.pause.between_posts = 2.0;
+        config.pause.between_comments = 3.0;
+
+        let pause = |current, next| pause_between(&config, current, next).as_secs_f32();
+        assert_eq!(pause(TextKind::Title, TextKind::Body), 1.0);
+        assert_eq!(pause(TextKind::Intro, TextKind::Title), 1.0);
+        assert_eq!(pause(TextKind::Title, TextKind::Title), 2.0);
+        assert_eq!(pause(TextKind::Body, TextKind::Title), 2.0);
+        assert_eq!(pause(TextKind::Comment, TextKind::Comment), 3.0);
+    }
+}
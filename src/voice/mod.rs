@@ -1,16 +1,28 @@
 use regex::Regex;
 use std::{io, time::Duration};
 
-use crate::config;
+use crate::{config, video, Text, TextKind};
+
+/// Speech characters per second, at normal rate
+const CHARS_PER_SECOND: f32 = 15.0;
 
 #[derive(Debug)]
 pub struct Voice {
-    pub text: String,
+    pub text: Text,
     pub bytes: Vec<u8>,
     pub duration: Duration,
+    /// Duration is estimated from text length, and audio was not fetched (such as for dry run)
+    pub estimated: bool,
+}
+
+impl Voice {
+    /// Whether voice has no speech, and is rendered as silence
+    pub fn is_silent(&self) -> bool {
+        self.bytes.is_empty() && !self.estimated
+    }
 }
 
-pub fn create_voices(config: &config::Voice, texts: Vec<String>) -> Result<Vec<Voice>, String> {
+pub fn create_voices(config: &config::Voice, texts: Vec<Text>) -> Result<Vec<Voice>, String> {
     let mut voices = Vec::new();
     for text in texts {
         voices.push(create_voice(config, text)?);
@@ -18,7 +30,105 @@ pub fn create_voices(config: &config::Voice, texts: Vec<String>) -> Result<Vec<V
     Ok(voices)
 }
 
-fn create_voice(config: &config::Voice, text: String) -> Result<Voice, String> {
+/// Create voices without fetching audio, estimating duration from length of text
+pub fn estimate_voices(config: &config::Voice, texts: Vec<Text>) -> Vec<Voice> {
+    texts
+        .into_iter()
+        .map(|text| {
+            let duration = estimate(config, &text.content);
+            Voice {
+                text,
+                bytes: Vec::new(),
+                duration,
+                estimated: true,
+            }
+        })
+        .collect()
+}
+
+/// Create intro frame, showing title card of first post, if enabled
+///
+/// Intro is spoken if speech is set, otherwise silent for configured length
+pub fn create_intro(
+    config: &config::Voice,
+    intro: &config::Intro,
+    texts: &[Text],
+) -> Result<Option<Voice>, String> {
+    intro_voice(intro, texts, false, |speech| synthesize(config, speech))
+}
+
+/// Create intro frame without fetching audio, estimating duration of speech
+pub fn estimate_intro(
+    config: &config::Voice,
+    intro: &config::Intro,
+    texts: &[Text],
+) -> Option<Voice> {
+    intro_voice(intro, texts, true, |speech| {
+        Ok((Vec::new(), estimate(config, speech)))
+    })
+    .expect("Estimating voice cannot fail")
+}
+
+/// Create intro frame, with speech created by `speak`
+fn intro_voice(
+    intro: &config::Intro,
+    texts: &[Text],
+    estimated: bool,
+    speak: impl FnOnce(&str) -> Result<(Vec<u8>, Duration), String>,
+) -> Result<Option<Voice>, String> {
+    if !intro.enabled {
+        return Ok(None);
+    }
+    let Some(title) = texts.iter().find(|text| text.kind == TextKind::Title) else {
+        return Ok(None);
+    };
+    let text = Text {
+        kind: TextKind::Intro,
+        ..title.clone()
+    };
+
+    let Some(speech) = &intro.speech else {
+        return Ok(Some(Voice {
+            text,
+            bytes: Vec::new(),
+            duration: video::duration_from_secs(intro.length),
+            estimated: false,
+        }));
+    };
+
+    let subreddit = title
+        .source
+        .as_ref()
+        .map_or("", |source| source.subreddit.as_str());
+    let (bytes, duration) = speak(&speech.replace("{subreddit}", subreddit))?;
+
+    Ok(Some(Voice {
+        text,
+        bytes,
+        duration,
+        estimated,
+    }))
+}
+
+fn create_voice(config: &config::Voice, text: Text) -> Result<Voice, String> {
+    let (bytes, duration) = synthesize(config, &text.content)?;
+    Ok(Voice {
+        text,
+        bytes,
+        duration,
+        estimated: false,
+    })
+}
+
+/// Estimate duration of speech from length of text, scaled by rate (0.5 is normal speed)
+fn estimate(config: &config::Voice, text: &str) -> Duration {
+    let chars = remove_emojis(text).chars().count() as f32;
+    let rate = (config.rate / 0.5).max(0.1);
+    video::duration_from_secs((chars / (CHARS_PER_SECOND * rate)).max(0.5))
+}
+
+/// Fetch speech audio of text, and get its duration
+fn synthesize(config: &config::Voice, text: &str) -> Result<(Vec<u8>, Duration), String> {
     let config::Voice {
         language,
         gender,
@@ -26,7 +136,7 @@ fn create_voice(config: &config::Voice, text: String) -> Result<Voice, String> {
         rate,
     } = config;
 
-    let text_filtered = remove_emojis(&text);
+    let text_filtered = remove_emojis(text);
 
     let url = format!("https://texttospeech.responsivevoice.org/v1/text:synthesize?text={text_filtered}&lang={language}&engine=g1&name=&pitch={pitch}&rate={rate}&volume=1&key=kvfbSITh&gender={gender}");
 
@@ -47,13 +157,7 @@ fn create_voice(config: &config::Voice, text: String) -> Result<Voice, String> {
         i += 1;
 
         match attempt() {
-            Ok((bytes, duration)) => {
-                return Ok(Voice {
-                    bytes,
-                    duration,
-                    text,
-                })
-            }
+            Ok(result) => return Ok(result),
 
             Err(err) => {
                 eprintln!(
@@ -87,5 +191,5 @@ fn remove_emojis(text: &str) -> String {
     ))
     .unwrap();
 
-    regex.replace_all(&text, "").to_string()
+    regex.replace_all(text, "").to_string()
 }